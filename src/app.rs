@@ -1,9 +1,19 @@
-use std::error::Error;
+use std::{error::Error, sync::Arc};
 
+use slint::Model;
 use tokio::sync::broadcast::Sender;
 use crate::{
+    arp::Pattern,
     message::Message,
-    synth::{mixer::SynthMode, osc::{self, oscillator}}
+    synth::{
+        amp::{AmpEnvelope, Curve},
+        mixer::SynthMode,
+        multistage::Stage,
+        osc::{self, noise, oscillator, sample::{LoopMode, Sample}, unison, wavetable::Wavetable},
+        routing::{Algorithm, ModKind, Route},
+        tuning::{KeyboardMapping, Scale, Tuning},
+        NUM_OSCS,
+    }
 };
 
 // rust analyzer might flag the following macro as an error, but the project should still compile successfully:
@@ -31,12 +41,115 @@ pub fn run(tx: Sender<Message>) -> Result<(), Box<dyn Error>> {
         let _ = tx3.send(msg);
     });
 
+    let tx12 = tx.clone();
+
+    main_window.on_arp_prop_changed(move |prop, value| {
+        let msg = generate_arp_msg(prop, value);
+        let _ = tx12.send(msg);
+    });
+
     let tx4 = tx.clone();
 
     main_window.on_midi_reset(move || {
         let _ = tx4.send(Message::ResetMIDI());
     });
 
+    let tx5 = tx.clone();
+
+    main_window.on_osc_route_changed(move |source, target, kind, depth| {
+        // Index values are hardcoded in app.slint -- if these casts fail, something is very wrong.
+        let msg = generate_route_msg(source as usize, target as usize, kind, depth);
+        let _ = tx5.send(msg);
+    });
+
+    let tx6 = tx.clone();
+    let weak = main_window.as_weak();
+
+    main_window.on_algorithm_changed(move |index| {
+        let algorithm = match index {
+            1 => Algorithm::Serial,
+            2 => Algorithm::Pairs,
+            3 => Algorithm::ThreeToOne,
+            4 => Algorithm::StackPlusOne,
+            5 => Algorithm::OneToThree,
+            6 => Algorithm::Diamond,
+            _ => Algorithm::Parallel,
+        };
+        let _ = tx6.send(Message::Algorithm(algorithm));
+
+        if let Some(main_window) = weak.upgrade() {
+            show_algorithm(&main_window, algorithm);
+        }
+    });
+
+    let tx7 = tx.clone();
+
+    main_window.on_osc_wavetable_loaded(move |index, path| {
+        // Index values are hardcoded in app.slint -- if this cast fails, something is very wrong.
+        let index = index as usize;
+        match Wavetable::load(path.as_str()) {
+            Ok(wavetable) => {
+                let name = wavetable.path().and_then(|path| path.file_name()).unwrap_or_default().to_string_lossy();
+                let status = format!("Loaded {name} ({} frame(s))", wavetable.frames());
+                let _ = tx7.send(Message::Wavetable { oscillator: index, wavetable: Arc::new(wavetable) });
+                status.into()
+            }
+            Err(e) => format!("Error: {e}").into(),
+        }
+    });
+
+    let tx8 = tx.clone();
+
+    main_window.on_osc_harmonics_changed(move |index, amplitudes, phases| {
+        // Index values are hardcoded in app.slint -- if this cast fails, something is very wrong.
+        let index = index as usize;
+        let amplitudes: Vec<f64> = amplitudes.iter().map(f64::from).collect();
+        let phases: Vec<f64> = phases.iter().map(f64::from).collect();
+
+        // the table is built here so the audio thread only has to swap it in:
+        let wavetable = Arc::new(Wavetable::from_harmonics(&amplitudes, &phases));
+        let _ = tx8.send(Message::Additive { oscillator: index, wavetable });
+    });
+
+    let tx9 = tx.clone();
+
+    main_window.on_osc_sample_loaded(move |index, path| {
+        // Index values are hardcoded in app.slint -- if this cast fails, something is very wrong.
+        let index = index as usize;
+        match Sample::load(path.as_str()) {
+            Ok(sample) => {
+                let name = sample.path().file_name().unwrap_or_default().to_string_lossy();
+                let status = format!("Loaded {name} ({} samples)", sample.len());
+                let _ = tx9.send(Message::Sample { oscillator: index, sample: Arc::new(sample) });
+                status.into()
+            }
+            Err(e) => format!("Error: {e}").into(),
+        }
+    });
+
+    let tx10 = tx.clone();
+
+    main_window.on_tuning_changed(move |scale, mapping, reference| {
+        match load_tuning(scale.as_str(), mapping.as_str(), reference.as_str()) {
+            Ok((tuning, status)) => {
+                let _ = tx10.send(Message::Tuning(Arc::new(tuning)));
+                status.into()
+            }
+            Err(e) => format!("Error: {e}").into(),
+        }
+    });
+
+    let tx11 = tx.clone();
+
+    main_window.on_stages_changed(move |points, count, sustain, loop_start, loop_end| {
+        let points: Vec<StagePoint> = points.iter().take(count.max(0) as usize).collect();
+        let stages = stages_from_points(&points);
+        // stages are numbered from 1 in stages.slint, with 0 meaning none:
+        let sustain = usize::try_from(sustain - 1).ok();
+        let looping = usize::try_from(loop_start - 1).ok().zip(usize::try_from(loop_end - 1).ok());
+        let _ = tx11.send(Message::Stages { stages, sustain, looping });
+    });
+
     main_window.on_set_precision(|value, precision| {
         format!("{value:.0$}", usize::try_from(precision).unwrap_or(0)).into()
     });
@@ -48,6 +161,107 @@ pub fn run(tx: Sender<Message>) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+/// Builds a [`Tuning`] from the Scala scale and keyboard mapping files at `scale_path` and `mapping_path`, and
+/// returns it with a status message describing it. An empty path uses 12-tone equal temperament or a linear
+/// mapping from middle C respectively. If `reference` isn't empty, it replaces the frequency of the mapping's
+/// reference note (A4 without a mapping file, or 440Hz if neither is given).
+fn load_tuning(scale_path: &str, mapping_path: &str, reference: &str) -> Result<(Tuning, String), Box<dyn Error>> {
+    let scale = match scale_path.trim() {
+        "" => Scale::equal_temperament(),
+        path => Scale::load(path)?,
+    };
+    let mut mapping = match mapping_path.trim() {
+        "" => KeyboardMapping::linear(440.0),
+        path => KeyboardMapping::load(path)?,
+    };
+    if !reference.trim().is_empty() {
+        mapping.set_reference_frequency(reference.trim().parse()?);
+    }
+
+    let tuning = Tuning::new(&scale, &mapping)?;
+    let status = format!(
+        "{} ({} notes), MIDI note {} = {:.2}Hz",
+        scale.description(), scale.len(), mapping.reference_note(), mapping.reference_frequency()
+    );
+    Ok((tuning, status))
+}
+
+/// Converts the points of the multi-stage envelope editor in `stages.slint`, whose times are measured from the
+/// start of the envelope, to [`Stage`]s, whose times are measured from the end of the previous `Stage`.
+fn stages_from_points(points: &[StagePoint]) -> Vec<Stage> {
+    let mut previous = 0.0;
+    points.iter()
+        .map(|point| {
+            let time = f64::from(point.time);
+            let stage = Stage { curve: curve(point.tension.into()), level: point.level.into(), time: time - previous };
+            previous = time;
+            stage
+        })
+        .collect()
+}
+
+/// Updates every `Oscillator`'s routing controls to match `algorithm`, since selecting one replaces the routing
+/// without going through those controls.
+fn show_algorithm(main_window: &MainWindow, algorithm: Algorithm) {
+    for (source, master) in algorithm.master().into_iter().enumerate() {
+        main_window.invoke_set_amp_send(source as i32, master);
+
+        for target in 0..NUM_OSCS {
+            // matches the "FM" entry of the route type ComboBox in oscillator.slint:
+            let kind = if algorithm.routes().contains(&(source, target)) { 1 } else { 0 };
+            main_window.invoke_set_route(source as i32, target as i32, kind);
+        }
+    }
+}
+
+/// Generates a [`Message`] to send to other tasks based on changes made to [`Arpeggiator`] properties.
+/// 
+/// [`Arpeggiator`]:    crate::arp::Arpeggiator
+fn generate_arp_msg(prop: ArpProps, value: f32) -> Message {
+    match prop {
+        ArpProps::Enabled => {
+            Message::ArpEnabled(value != 0.0)
+        }
+        ArpProps::Gate => {
+            Message::ArpGate(value.into())
+        }
+        ArpProps::Latch => {
+            Message::ArpLatch(value != 0.0)
+        }
+        ArpProps::Octaves => unsafe {
+            Message::ArpOctaves(value.to_int_unchecked())
+        }
+        ArpProps::Pattern => unsafe {
+            let pattern = match value.to_int_unchecked() {
+                1 => Pattern::Down,
+                2 => Pattern::UpDown,
+                3 => Pattern::Random,
+                4 => Pattern::AsPlayed,
+                _ => Pattern::Up,
+            };
+            Message::ArpPattern(pattern)
+        }
+        ArpProps::Rate => unsafe {
+            // the divisions in arpeggiator.slint, as steps per beat:
+            let rate = match value.to_int_unchecked() {
+                0 => 1.0,
+                2 => 3.0,
+                3 => 4.0,
+                4 => 6.0,
+                5 => 8.0,
+                _ => 2.0,
+            };
+            Message::ArpRate(rate)
+        }
+        ArpProps::Sync => {
+            Message::ArpSync(value != 0.0)
+        }
+        ArpProps::Tempo => {
+            Message::ArpTempo(value.into())
+        }
+    }
+}
+
 /// Generates a [`Message`] to send to other tasks based on changes made to [`Amplifier`] properties.
 /// 
 /// [`Amplifier`]:  crate::synth::amp::Amplifier
@@ -56,15 +270,36 @@ fn generate_amp_msg(prop: AmpProps, value: f32) -> Message {
         AmpProps::Attack => {
             Message::Attack(value.into())
         }
+        AmpProps::AttackCurve => {
+            Message::AttackCurve(curve(value.into()))
+        }
         AmpProps::Decay => {
             Message::Decay(value.into())
         }
+        AmpProps::DecayCurve => {
+            Message::DecayCurve(curve(value.into()))
+        }
         AmpProps::Sustain => {
             Message::Sustain(value.into())
         }
         AmpProps::Release => {
             Message::Release(value.into())
         }
+        AmpProps::ReleaseCurve => {
+            Message::ReleaseCurve(curve(value.into()))
+        }
+        AmpProps::Delay => {
+            Message::Delay(value.into())
+        }
+        AmpProps::Hold => {
+            Message::Hold(value.into())
+        }
+        AmpProps::KeyScaling => {
+            Message::KeyScaling(value.into())
+        }
+        AmpProps::VelocityScaling => {
+            Message::VelocityScaling(value.into())
+        }
         AmpProps::Gain => {
             Message::Master(value.into())
         }
@@ -76,6 +311,50 @@ fn generate_amp_msg(prop: AmpProps, value: f32) -> Message {
             };
             Message::MixerMode(value)
         }
+        AmpProps::Envelope => unsafe {
+            let envelope = match value.to_int_unchecked() {
+                1 => AmpEnvelope::MultiStage,
+                _ => AmpEnvelope::Dahdsr,
+            };
+            Message::AmpEnvelope(envelope)
+        }
+    }
+}
+
+/// Converts the tension sent by a curve selector in `amplifier.slint` (or a stage in `stages.slint`) to a
+/// [`Curve`], where each named curve sends the tension it's equal to.
+fn curve(tension: f64) -> Curve {
+    if tension == 0.0 {
+        Curve::Linear
+    } else if tension == 1.0 {
+        Curve::Exponential
+    } else if tension == -1.0 {
+        Curve::Logarithmic
+    } else {
+        Curve::Tension(tension)
+    }
+}
+
+/// Generates a [`Message`] to send to other tasks based on changes made to an [`Oscillator`]'s routing.
+/// 
+/// `kind` is the index of the modulation type selected in `oscillator.slint`, where 0 disconnects the
+/// `Oscillator`s.
+/// 
+/// [`Oscillator`]: oscillator::Oscillator
+fn generate_route_msg(source: usize, target: usize, kind: i32, depth: f32) -> Message {
+    let kind = match kind {
+        1 => Some(ModKind::Fm),
+        2 => Some(ModKind::Pwm),
+        3 => Some(ModKind::Sync),
+        4 => Some(ModKind::Am),
+        5 => Some(ModKind::Ring),
+        _ => None,
+    };
+
+    Message::Route {
+        source,
+        target,
+        route: kind.map(|kind| Route { kind, depth: depth.into() }),
     }
 }
 
@@ -84,6 +363,48 @@ fn generate_amp_msg(prop: AmpProps, value: f32) -> Message {
 /// [`Oscillator`]: oscillator::Oscillator
 fn generate_osc_msg(index: usize, prop: OscProps, value: f32) -> Message {
     match prop {
+        OscProps::AmDepth => {
+            Message::AmDepth {
+                oscillator: index,
+                depth: value.into()
+            }
+        }
+        OscProps::AmpAttack => {
+            Message::OscAmpAttack {
+                oscillator: index,
+                attack: value.into()
+            }
+        }
+        OscProps::AmpDecay => {
+            Message::OscAmpDecay {
+                oscillator: index,
+                decay: value.into()
+            }
+        }
+        OscProps::AmpEnabled => {
+            Message::OscAmpEnabled {
+                oscillator: index,
+                enabled: value != 0.0
+            }
+        }
+        OscProps::AmpRelease => {
+            Message::OscAmpRelease {
+                oscillator: index,
+                release: value.into()
+            }
+        }
+        OscProps::AmpSend => {
+            Message::AmpSend {
+                oscillator: index,
+                send: value != 0.0
+            }
+        }
+        OscProps::AmpSustain => {
+            Message::OscAmpSustain {
+                oscillator: index,
+                sustain: value.into()
+            }
+        }
         OscProps::Bypass => {
             let value = match value {
                 0.0 => false,
@@ -101,18 +422,69 @@ fn generate_osc_msg(index: usize, prop: OscProps, value: f32) -> Message {
                 coarse: value.to_int_unchecked()
             }
         }
+        OscProps::Drift => {
+            Message::Drift {
+                oscillator: index,
+                drift: value.into()
+            }
+        }
+        OscProps::EnvAmount => {
+            Message::EnvAmount {
+                oscillator: index,
+                amount: value.into()
+            }
+        }
+        OscProps::EnvTarget => unsafe {
+            let target = match value.to_int_unchecked() {
+                1 => oscillator::EnvTarget::PulseWidth,
+                2 => oscillator::EnvTarget::Morph,
+                _ => oscillator::EnvTarget::Pitch,
+            };
+            Message::EnvTarget {
+                oscillator: index,
+                target
+            }
+        }
+        OscProps::Feedback => {
+            Message::Feedback {
+                oscillator: index,
+                feedback: value.into()
+            }
+        }
         OscProps::Fine => {
             Message::Fine {
                 oscillator: index,
                 fine: value.into()
             }
         }
+        OscProps::FmIndex => {
+            Message::FmIndex {
+                oscillator: index,
+                index: value.into()
+            }
+        }
+        OscProps::FmMode => unsafe {
+            let value = match value.to_int_unchecked() {
+                1 => oscillator::FmMode::Ratio,
+                _ => oscillator::FmMode::Linear,
+            };
+            Message::FmMode {
+                oscillator: index,
+                mode: value
+            }
+        }
         OscProps::FmRange => unsafe {
             Message::FmRange {
                 oscillator: index,
                 range: value.to_int_unchecked()
             }
         }
+        OscProps::FmRatio => {
+            Message::FmRatio {
+                oscillator: index,
+                ratio: value.into()
+            }
+        }
         OscProps::Freq => {
             Message::Freq {
                 oscillator: index,
@@ -125,6 +497,12 @@ fn generate_osc_msg(index: usize, prop: OscProps, value: f32) -> Message {
                 gain: value.into()
             }
         }
+        OscProps::KeyTrack => {
+            Message::KeyTrack {
+                oscillator: index,
+                key_track: value.into()
+            }
+        }
         OscProps::Mode => unsafe {
             let value = match value.to_int_unchecked() {
                 0 => oscillator::PitchMode::MIDI,
@@ -136,25 +514,187 @@ fn generate_osc_msg(index: usize, prop: OscProps, value: f32) -> Message {
                 mode: value
             }
         }
-        OscProps::Output => unsafe {
-            let value = match value.to_int_unchecked() {
-                j if
-                    (1..=4).contains(&j)
-                    && j as usize - 1 != index => oscillator::OutputMode::Osc(j as usize - 1),
-                _ => oscillator::OutputMode::Master,
+        OscProps::MorphAmount => {
+            Message::MorphAmount {
+                oscillator: index,
+                amount: value.into()
+            }
+        }
+        OscProps::MorphAttack => {
+            Message::MorphAttack {
+                oscillator: index,
+                attack: value.into()
+            }
+        }
+        OscProps::MorphDecay => {
+            Message::MorphDecay {
+                oscillator: index,
+                decay: value.into()
+            }
+        }
+        OscProps::MorphPosition => {
+            Message::MorphPosition {
+                oscillator: index,
+                position: value.into()
+            }
+        }
+        OscProps::MorphSustain => {
+            Message::MorphSustain {
+                oscillator: index,
+                sustain: value.into()
+            }
+        }
+        OscProps::NoiseColor => unsafe {
+            let color = match value.to_int_unchecked() {
+                1 => noise::NoiseColor::Pink,
+                2 => noise::NoiseColor::Brown,
+                3 => noise::NoiseColor::Blue,
+                _ => noise::NoiseColor::White,
             };
-
-            Message::Output {
+            Message::NoiseColor {
                 oscillator: index,
-                mode: value
+                color
+            }
+        }
+        OscProps::NoiseKeyTrack => {
+            Message::NoiseKeyTrack {
+                oscillator: index,
+                key_track: value != 0.0
+            }
+        }
+        OscProps::NoiseSeed => unsafe {
+            Message::NoiseSeed {
+                oscillator: index,
+                seed: value.to_int_unchecked()
+            }
+        }
+        OscProps::Octave => unsafe {
+            Message::Octave {
+                oscillator: index,
+                octave: value.to_int_unchecked()
+            }
+        }
+        OscProps::PhaseMode => unsafe {
+            let mode = match value.to_int_unchecked() {
+                1 => oscillator::PhaseMode::Random,
+                2 => oscillator::PhaseMode::FreeRunning,
+                _ => oscillator::PhaseMode::Reset,
+            };
+            Message::PhaseMode {
+                oscillator: index,
+                mode
+            }
+        }
+        OscProps::PluckBrightness => {
+            Message::PluckBrightness {
+                oscillator: index,
+                brightness: value.into()
+            }
+        }
+        OscProps::PluckDamping => {
+            Message::PluckDamping {
+                oscillator: index,
+                damping: value.into()
+            }
+        }
+        OscProps::PluckPosition => {
+            Message::PluckPosition {
+                oscillator: index,
+                position: value.into()
+            }
+        }
+        OscProps::PulseWidth => {
+            Message::PulseWidth {
+                oscillator: index,
+                width: value.into()
+            }
+        }
+        OscProps::PwmRange => {
+            Message::PwmRange {
+                oscillator: index,
+                range: value.into()
+            }
+        }
+        OscProps::SampleLoopEnd => {
+            Message::SampleLoopEnd {
+                oscillator: index,
+                end: value.into()
+            }
+        }
+        OscProps::SampleLoopMode => unsafe {
+            let mode = match value.to_int_unchecked() {
+                1 => LoopMode::Forward,
+                2 => LoopMode::PingPong,
+                _ => LoopMode::Off,
+            };
+            Message::SampleLoopMode {
+                oscillator: index,
+                mode
+            }
+        }
+        OscProps::SampleLoopStart => {
+            Message::SampleLoopStart {
+                oscillator: index,
+                start: value.into()
+            }
+        }
+        OscProps::SampleRoot => unsafe {
+            Message::SampleRoot {
+                oscillator: index,
+                root: value.to_int_unchecked()
+            }
+        }
+        OscProps::SampleStart => {
+            Message::SampleStart {
+                oscillator: index,
+                start: value.into()
+            }
+        }
+        OscProps::StartPhase => {
+            Message::StartPhase {
+                oscillator: index,
+                phase: value.into()
+            }
+        }
+        OscProps::Unison => unsafe {
+            Message::Unison {
+                oscillator: index,
+                voices: value.to_int_unchecked()
+            }
+        }
+        OscProps::UnisonCurve => unsafe {
+            let curve = match value.to_int_unchecked() {
+                1 => unison::DetuneCurve::Exponential,
+                _ => unison::DetuneCurve::Linear,
+            };
+            Message::UnisonCurve {
+                oscillator: index,
+                curve
+            }
+        }
+        OscProps::UnisonDetune => {
+            Message::UnisonDetune {
+                oscillator: index,
+                detune: value.into()
+            }
+        }
+        OscProps::UnisonSpread => {
+            Message::UnisonSpread {
+                oscillator: index,
+                spread: value.into()
             }
         }
         OscProps::Waveform => unsafe {
             let waveform = match value.to_int_unchecked() {
                 0 => osc::wave::Waveform::Noise,
-                1 => osc::wave::Waveform::Saw,
-                3 => osc::wave::Waveform::Square,
-                4 => osc::wave::Waveform::Triangle,
+                1 => osc::wave::Waveform::Pulse,
+                2 => osc::wave::Waveform::Saw,
+                4 => osc::wave::Waveform::Square,
+                5 => osc::wave::Waveform::Triangle,
+                6 => osc::wave::Waveform::Custom,
+                7 => osc::wave::Waveform::Additive,
+                8 => osc::wave::Waveform::Pluck,
+                9 => osc::wave::Waveform::Sample,
                 _ => osc::wave::Waveform::Sine, // just set to Sine if something goes wrong?
             };
             Message::Waveform {
@@ -0,0 +1,424 @@
+use crate::{message::Message, synth::osc::noise::{self, Prng}};
+
+/// The number of MIDI clock messages sent per beat (quarter note).
+const CLOCKS_PER_BEAT: f64 = 24.0;
+
+/// How close (in steps) the arpeggiator has to come to the end of a step to start the next, so that clock
+/// messages adding up to a whole step aren't lost to rounding.
+const STEP_TOLERANCE: f64 = 1e-9;
+
+/// Turns the notes held on the MIDI input into a repeating sequence of single notes, which it sends to [`synth`]
+/// in their place.
+/// 
+/// Each held note is played in turn, in the order given by its [`Pattern`], across `octaves` octaves. A step lasts
+/// `1 / rate` beats, and each note sounds for `gate` of its step. Beats are either counted from incoming MIDI
+/// clock messages when `sync` is `true`, or timed at `tempo` BPM otherwise. With `latch` on, the sequence keeps
+/// playing after every key is released, until a new chord replaces it.
+/// 
+/// Every method returns the [`Message`]s the arpeggiator wants sent in response. While it's disabled, notes pass
+/// straight through.
+/// 
+/// [`synth`]:  crate::synth
+pub struct Arpeggiator {
+    /// Whether notes are arpeggiated, rather than passed straight through.
+    enabled: bool,
+    /// How much `[0..1]` of each step a note sounds for.
+    gate: f64,
+    /// The MIDI pitches and velocities of the keys currently held, in the order they were pressed.
+    held: Vec<(u8, u8)>,
+    /// Whether the sequence keeps playing after its keys are released.
+    latch: bool,
+    /// The MIDI pitches and velocities being arpeggiated, in the order they were played.
+    notes: Vec<(u8, u8)>,
+    /// How many octaves `[1..4]` the sequence spans, repeating the notes an octave higher each time.
+    octaves: u8,
+    /// The order the notes are played in.
+    pattern: Pattern,
+    /// How far `[0..1]` through the current step the arpeggiator is.
+    phase: f64,
+    /// The MIDI pitch of the note currently sounding, if there is one.
+    playing: Option<u8>,
+    /// The index of the next step in the sequence.
+    position: usize,
+    /// The number of steps per beat, e.g. 4 for sixteenth notes.
+    rate: f64,
+    /// Chooses the notes of [`Pattern::Random`].
+    rng: Prng,
+    /// Whether beats are counted from MIDI clock messages, rather than timed at `tempo`.
+    sync: bool,
+    /// The speed (in BPM) of the beat when not synced to MIDI clock.
+    tempo: f64,
+}
+
+impl Arpeggiator {
+    /// Returns a new, disabled `Arpeggiator`, set to play eighth notes upwards across one octave at 120 BPM.
+    pub fn new() -> Arpeggiator {
+        Arpeggiator {
+            enabled: false,
+            gate: 0.5,
+            held: Vec::new(),
+            latch: false,
+            notes: Vec::new(),
+            octaves: 1,
+            pattern: Pattern::Up,
+            phase: 0.0,
+            playing: None,
+            position: 0,
+            rate: 2.0,
+            rng: Prng::new(noise::DEFAULT_SEED),
+            sync: false,
+            tempo: 120.0,
+        }
+    }
+
+    /// Adds a held note to the sequence, starting the sequence from its first step if nothing was playing. With
+    /// `self.latch` on, the first key pressed after every key was released starts a new sequence.
+    /// 
+    /// Many keyboards end notes with a note-on at velocity 0, which is handled as [`note_off()`].
+    /// 
+    /// [`note_off()`]: Arpeggiator::note_off()
+    pub fn note_on(&mut self, pitch: u8, velocity: u8) -> Vec<Message> {
+        if velocity == 0 {
+            return self.note_off(pitch);
+        }
+        if !self.enabled {
+            return vec![Message::NoteOn { pitch, velocity }];
+        }
+        if self.held.iter().any(|&(held, _)| held == pitch) {
+            return Vec::new();
+        }
+
+        if self.latch && self.held.is_empty() {
+            self.notes.clear();
+        }
+        self.held.push((pitch, velocity));
+        self.notes.push((pitch, velocity));
+
+        let mut messages = Vec::new();
+        if self.notes.len() == 1 {
+            self.position = 0;
+            self.phase = 0.0;
+            self.step(&mut messages);
+        }
+        messages
+    }
+
+    /// Removes a released note from the sequence, unless `self.latch` is on, stopping the sequence if it was the
+    /// last. Notes the arpeggiator never received (such as those held since before it was enabled) are passed
+    /// straight through.
+    pub fn note_off(&mut self, pitch: u8) -> Vec<Message> {
+        if !self.enabled || !self.held.iter().any(|&(held, _)| held == pitch) {
+            return vec![Message::NoteOff { pitch }];
+        }
+
+        self.held.retain(|&(held, _)| held != pitch);
+        let mut messages = Vec::new();
+        if !self.latch {
+            self.notes.retain(|&(note, _)| note != pitch);
+            if self.notes.is_empty() {
+                self.release(&mut messages);
+            }
+        }
+        messages
+    }
+
+    /// Advances the arpeggiator by `seconds` when it isn't synced to MIDI clock.
+    pub fn tick(&mut self, seconds: f64) -> Vec<Message> {
+        if self.sync {
+            Vec::new()
+        } else {
+            self.advance(seconds * self.tempo / 60.0 * self.rate)
+        }
+    }
+
+    /// Advances the arpeggiator by one MIDI clock message (a 24th of a beat) when it's synced to MIDI clock.
+    pub fn clock(&mut self) -> Vec<Message> {
+        if self.sync {
+            self.advance(self.rate / CLOCKS_PER_BEAT)
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Handles a MIDI start message, restarting the sequence so that its first step falls on the next clock.
+    pub fn start(&mut self) {
+        self.position = 0;
+        self.phase = 1.0 - self.rate / CLOCKS_PER_BEAT;
+    }
+
+    /// Handles a MIDI stop message, silencing the current note until the clock continues.
+    pub fn stop(&mut self) -> Vec<Message> {
+        let mut messages = Vec::new();
+        self.release(&mut messages);
+        messages
+    }
+
+    /// Returns whether the arpeggiator is timed by [`tick()`] calls: when it's enabled and not synced to MIDI
+    /// clock.
+    /// 
+    /// [`tick()`]: Arpeggiator::tick()
+    pub fn is_timed(&self) -> bool {
+        self.enabled && !self.sync
+    }
+
+    /// Replaces `self.enabled`. Disabling the arpeggiator silences it and forgets its sequence.
+    pub fn set_enabled(&mut self, enabled: bool) -> Vec<Message> {
+        let mut messages = Vec::new();
+        if self.enabled && !enabled {
+            self.release(&mut messages);
+            self.held.clear();
+            self.notes.clear();
+        }
+        self.enabled = enabled;
+        messages
+    }
+
+    /// Restricts `gate` to the range `[0.01..1]`, then replaces `self.gate`.
+    pub fn set_gate(&mut self, gate: f64) {
+        self.gate = gate.clamp(0.01, 1.0);
+    }
+
+    /// Replaces `self.latch`. Turning it off drops every note that isn't still held.
+    pub fn set_latch(&mut self, latch: bool) -> Vec<Message> {
+        self.latch = latch;
+
+        let mut messages = Vec::new();
+        if !latch {
+            self.notes = self.held.clone();
+            if self.notes.is_empty() {
+                self.release(&mut messages);
+            }
+        }
+        messages
+    }
+
+    /// Restricts `octaves` to the range `[1..4]`, then replaces `self.octaves`.
+    pub fn set_octaves(&mut self, octaves: u8) {
+        self.octaves = octaves.clamp(1, 4);
+    }
+
+    /// Replaces `self.pattern`.
+    pub fn set_pattern(&mut self, pattern: Pattern) {
+        self.pattern = pattern;
+    }
+
+    /// Restricts `rate` to the range `[0.25..24]` steps per beat, then replaces `self.rate`.
+    pub fn set_rate(&mut self, rate: f64) {
+        self.rate = rate.clamp(0.25, CLOCKS_PER_BEAT);
+    }
+
+    /// Replaces `self.sync`.
+    pub fn set_sync(&mut self, sync: bool) {
+        self.sync = sync;
+    }
+
+    /// Restricts `tempo` to the range `[20..300]` BPM, then replaces `self.tempo`.
+    pub fn set_tempo(&mut self, tempo: f64) {
+        self.tempo = tempo.clamp(20.0, 300.0);
+    }
+
+    /// Moves `steps` steps through the sequence, playing each step that starts and releasing each note whose gate
+    /// ends along the way.
+    fn advance(&mut self, steps: f64) -> Vec<Message> {
+        let mut messages = Vec::new();
+        if self.notes.is_empty() {
+            return messages;
+        }
+
+        self.phase += steps;
+        while self.phase >= 1.0 - STEP_TOLERANCE {
+            self.phase = (self.phase - 1.0).max(0.0);
+            self.step(&mut messages);
+        }
+        if self.phase >= self.gate {
+            self.release(&mut messages);
+        }
+        messages
+    }
+
+    /// Releases the current note, then plays the next note in the sequence.
+    fn step(&mut self, messages: &mut Vec<Message>) {
+        self.release(messages);
+
+        let sequence = self.sequence();
+        if sequence.is_empty() {
+            return;
+        }
+        let index = match self.pattern {
+            Pattern::Random => (self.rng.next_f64() * sequence.len() as f64) as usize,
+            _ => self.position % sequence.len(),
+        };
+        self.position = index + 1;
+
+        let (pitch, velocity) = sequence[index];
+        self.playing = Some(pitch);
+        messages.push(Message::NoteOn { pitch, velocity });
+    }
+
+    /// Sends a note-off for the current note, if there is one.
+    fn release(&mut self, messages: &mut Vec<Message>) {
+        if let Some(pitch) = self.playing.take() {
+            messages.push(Message::NoteOff { pitch });
+        }
+    }
+
+    /// Returns one cycle of the sequence: the notes in `self.notes` across `self.octaves` octaves, in the order
+    /// given by `self.pattern`. Notes transposed above the MIDI range are left out.
+    fn sequence(&self) -> Vec<(u8, u8)> {
+        let mut notes = self.notes.clone();
+        if self.pattern != Pattern::AsPlayed {
+            notes.sort_unstable();
+        }
+
+        let mut sequence: Vec<(u8, u8)> = (0..self.octaves)
+            .flat_map(|octave| {
+                notes.iter().map(move |&(pitch, velocity)| (pitch as u16 + 12 * octave as u16, velocity))
+            })
+            .filter(|&(pitch, _)| pitch <= 127)
+            .map(|(pitch, velocity)| (pitch as u8, velocity))
+            .collect();
+
+        match self.pattern {
+            Pattern::Down => sequence.reverse(),
+            // the top and bottom notes aren't repeated when the direction changes:
+            Pattern::UpDown if sequence.len() > 2 => {
+                let down: Vec<(u8, u8)> = sequence[1..sequence.len() - 1].iter().rev().copied().collect();
+                sequence.extend(down);
+            }
+            _ => (),
+        }
+        sequence
+    }
+}
+
+/// Describes the order an [`Arpeggiator`] plays its notes in.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Pattern {
+    /// From the lowest note to the highest.
+    Up,
+    /// From the highest note to the lowest.
+    Down,
+    /// Up, then back down, without repeating the highest and lowest notes.
+    UpDown,
+    /// A randomly chosen note each step.
+    Random,
+    /// In the order the keys were pressed.
+    AsPlayed,
+}
+
+#[cfg(test)]
+mod arp_tests {
+    use super::*;
+
+    /// Returns an enabled `Arpeggiator` playing `pattern` at one step per second, holding `pitches`.
+    fn holding(pattern: Pattern, pitches: &[u8]) -> Arpeggiator {
+        let mut arp = Arpeggiator::new();
+        let _ = arp.set_enabled(true);
+        arp.set_pattern(pattern);
+        arp.set_tempo(60.0);
+        arp.set_rate(1.0);
+        pitches.iter().for_each(|&pitch| { arp.note_on(pitch, 100); });
+        arp
+    }
+
+    /// Returns the pitches of every note-on in `messages`.
+    fn notes_on(messages: &[Message]) -> Vec<u8> {
+        messages.iter()
+            .filter_map(|message| match message {
+                Message::NoteOn { pitch, .. } => Some(*pitch),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Plays `steps` whole steps of `arp` (after its first), returning the pitch of each step.
+    fn play(arp: &mut Arpeggiator, steps: usize) -> Vec<u8> {
+        (0..steps).flat_map(|_| notes_on(&arp.tick(1.0))).collect()
+    }
+
+    #[test]
+    fn patterns_order_notes() {
+        let mut arp = holding(Pattern::Up, &[64, 60, 67]);
+        assert_eq!(play(&mut arp, 5), [64, 67, 60, 64, 67]);
+
+        let mut arp = holding(Pattern::Down, &[64, 60, 67]);
+        assert_eq!(play(&mut arp, 3), [64, 60, 67]);
+
+        let mut arp = holding(Pattern::UpDown, &[60]);
+        arp.set_octaves(3);
+        assert_eq!(play(&mut arp, 6), [72, 84, 72, 60, 72, 84]);
+
+        let mut arp = holding(Pattern::AsPlayed, &[64, 60, 67]);
+        assert_eq!(play(&mut arp, 3), [60, 67, 64]);
+
+        let mut arp = holding(Pattern::Random, &[64, 60, 67]);
+        assert!(play(&mut arp, 20).iter().all(|pitch| [60, 64, 67].contains(pitch)));
+    }
+
+    #[test]
+    fn gate_releases_each_note() {
+        let mut arp = holding(Pattern::Up, &[60]);
+        arp.set_gate(0.25);
+
+        assert!(arp.tick(0.2).is_empty());
+        assert!(matches!(arp.tick(0.1).as_slice(), [Message::NoteOff { pitch: 60 }]));
+        assert!(matches!(arp.tick(0.7).as_slice(), [Message::NoteOn { pitch: 60, velocity: 100 }]));
+    }
+
+    #[test]
+    fn latch_holds_until_new_chord() {
+        let mut arp = holding(Pattern::Up, &[60, 64]);
+        let _ = arp.set_latch(true);
+        arp.note_off(60);
+        arp.note_off(64);
+        assert_eq!(play(&mut arp, 2), [64, 60]);
+
+        // a new chord replaces the latched one, starting from its first step:
+        assert_eq!(notes_on(&arp.note_on(67, 100)), [67]);
+        assert_eq!(play(&mut arp, 2), [67, 67]);
+
+        // without latch, releasing every key stops the sequence:
+        let mut arp = holding(Pattern::Up, &[60]);
+        assert!(matches!(arp.note_off(60).as_slice(), [Message::NoteOff { pitch: 60 }]));
+        assert!(play(&mut arp, 2).is_empty());
+    }
+
+    #[test]
+    fn zero_velocity_releases_notes() {
+        let mut arp = holding(Pattern::Up, &[60, 64]);
+        arp.note_on(60, 0);
+        assert_eq!(play(&mut arp, 3), [64, 64, 64]);
+
+        assert!(matches!(arp.note_on(64, 0).as_slice(), [Message::NoteOff { pitch: 64 }]));
+        assert!(play(&mut arp, 2).is_empty());
+    }
+
+    #[test]
+    fn clock_sync_counts_24_per_beat() {
+        let mut arp = holding(Pattern::Up, &[60, 64]);
+        arp.set_sync(true);
+        arp.set_rate(4.0);
+        arp.start();
+
+        // free-running time is ignored once synced:
+        assert!(!arp.is_timed());
+        assert!(arp.tick(10.0).is_empty());
+        let steps: Vec<usize> = (0..24).filter(|_| !notes_on(&arp.clock()).is_empty()).collect();
+        assert_eq!(steps.len(), 4);
+    }
+
+    #[test]
+    fn disabled_passes_notes_through() {
+        let mut arp = Arpeggiator::new();
+        assert!(matches!(arp.note_on(60, 90).as_slice(), [Message::NoteOn { pitch: 60, velocity: 90 }]));
+
+        assert!(matches!(arp.note_on(60, 0).as_slice(), [Message::NoteOff { pitch: 60 }]));
+
+        assert!(!arp.is_timed());
+
+        // a note held from before the arpeggiator was enabled can still be released:
+        let _ = arp.set_enabled(true);
+        assert!(arp.is_timed());
+        assert!(matches!(arp.note_off(60).as_slice(), [Message::NoteOff { pitch: 60 }]));
+    }
+}
@@ -7,6 +7,7 @@ use tokio::sync::broadcast::{self};
 
 // modules:
 mod app;
+mod arp;
 mod message;
 mod midi;
 mod synth;
@@ -1,14 +1,26 @@
+use std::sync::Arc;
+
 #[allow(unused)]
 use crate::{
     app,
+    arp::{Arpeggiator, Pattern},
     midi,
     synth::{
-        amp::{Amplifier, Envelope},
+        amp::{AmpEnvelope, Amplifier, Curve, Envelope},
         mixer::{self, Mixer},
+        multistage::{MultiStage, Stage},
         osc::{
+            morph::Morph,
+            noise::NoiseColor,
             oscillator::{self, Oscillator},
+            pluck::Pluck,
+            sample::{LoopMode, Sample, Sampler},
+            unison::{DetuneCurve, Unison},
             wave::Waveform,
+            wavetable::Wavetable,
         },
+        routing::{Algorithm, Route, Routing},
+        tuning::Tuning,
     }
 };
 
@@ -20,9 +32,52 @@ use crate::{
 /// [`synth`]:      crate::synth
 #[derive(Clone,Debug)]
 pub enum Message {
+    /// Sent by the UI in [`app`] to replace the [`Wavetable`] played by an [`Oscillator`] set to
+    /// [`Waveform::Additive`], after its harmonics are edited.
+    Additive{oscillator: usize, wavetable: Arc<Wavetable>},
+
+    /// Sent by the UI in [`app`] to replace the whole [`Routing`] with the connections of an [`Algorithm`].
+    Algorithm(Algorithm),
+
+    /// Sent by the UI in [`app`] to modify the `am_depth` of an [`Oscillator`].
+    AmDepth{oscillator: usize, depth: f64},
+
+    /// Sent by the UI in [`app`] to modify which envelope shapes the amplitude of the [`Mixer`]'s [`Amplifier`].
+    AmpEnvelope(AmpEnvelope),
+
+    /// Sent by the UI in [`app`] to modify whether an [`Oscillator`] is sent to the [`Mixer`] in the [`Routing`].
+    AmpSend{oscillator: usize, send: bool},
+
+    /// Sent by the UI in [`app`] to turn the [`Arpeggiator`] on or off.
+    ArpEnabled(bool),
+
+    /// Sent by the UI in [`app`] to modify the `gate` length of the [`Arpeggiator`]'s notes.
+    ArpGate(f64),
+
+    /// Sent by the UI in [`app`] to set whether the [`Arpeggiator`] keeps playing after keys are released.
+    ArpLatch(bool),
+
+    /// Sent by the UI in [`app`] to modify the number of `octaves` the [`Arpeggiator`] spans.
+    ArpOctaves(u8),
+
+    /// Sent by the UI in [`app`] to modify the [`Pattern`] the [`Arpeggiator`] plays.
+    ArpPattern(Pattern),
+
+    /// Sent by the UI in [`app`] to modify the `rate` (in steps per beat) of the [`Arpeggiator`].
+    ArpRate(f64),
+
+    /// Sent by the UI in [`app`] to set whether the [`Arpeggiator`] follows MIDI clock.
+    ArpSync(bool),
+
+    /// Sent by the UI in [`app`] to modify the `tempo` (in BPM) of the [`Arpeggiator`] when it isn't synced.
+    ArpTempo(f64),
+
     /// Sent by the UI in [`app`] to modify the `attack` value of the [`Mixer`]'s [`Envelope`].
     Attack(f64),
 
+    /// Sent by the UI in [`app`] to modify the `attack_curve` of the [`Mixer`]'s [`Envelope`].
+    AttackCurve(Curve),
+
     /// Sent by the UI in [`app`] to modify the `bypass` value of an [`Oscillator`].
     Bypass{oscillator: usize, bypass: bool},
 
@@ -32,18 +87,57 @@ pub enum Message {
     /// Sent by the UI in [`app`] to modify the `decay` value of the [`Mixer`]'s [`Envelope`].
     Decay(f64),
 
-    /// Sent by the UI in [`app`] to modify the `fine` detune value of an [`Oscillator`].
+    /// Sent by the UI in [`app`] to modify the `decay_curve` of the [`Mixer`]'s [`Envelope`].
+    DecayCurve(Curve),
+
+    /// Sent by the UI in [`app`] to modify the `delay` value of the [`Mixer`]'s [`Envelope`].
+    Delay(f64),
+
+    /// Sent by the UI in [`app`] to modify how far (in cents) an [`Oscillator`]'s pitch can `drift`.
+    Drift{oscillator: usize, drift: f64},
+
+    /// Sent by the UI in [`app`] to modify how strongly the [`MultiStage`] envelope modulates an [`Oscillator`].
+    EnvAmount{oscillator: usize, amount: f64},
+
+    /// Sent by the UI in [`app`] to modify what the [`MultiStage`] envelope modulates in an [`Oscillator`].
+    EnvTarget{oscillator: usize, target: oscillator::EnvTarget},
+
+    /// Sent by the UI in [`app`] to modify the self-`feedback` amount of an [`Oscillator`].
+    Feedback{oscillator: usize, feedback: f64},
+
+    /// Sent by the UI in [`app`] to modify the `fine` detune value (in cents) of an [`Oscillator`].
     Fine{oscillator: usize, fine: f64},
 
+    /// Sent by the UI in [`app`] to modify the `fm_index` of an [`Oscillator`].
+    FmIndex{oscillator: usize, index: f64},
+
+    /// Sent by the UI in [`app`] to modify the `fm_mode` of an [`Oscillator`].
+    FmMode{oscillator: usize, mode: oscillator::FmMode},
+
     /// Sent by the UI in [`app`] to modify the `fm_range` of an [`Oscillator`].
     FmRange{oscillator: usize, range: u16},
 
+    /// Sent by the UI in [`app`] to modify the `fm_ratio` of an [`Oscillator`].
+    FmRatio{oscillator: usize, ratio: f64},
+
     /// Sent by the UI in [`app`] to modify the `frequency` value of an [`Oscillator`].
     Freq{oscillator: usize, freq: f64},
     
     /// Sent by the UI in [`app`] to modify the `gain` value of an [`Oscillator`]'s [`Amplifier`].
     Gain{oscillator: usize, gain: f64},
 
+    /// Sent by the UI in [`app`] to modify the `hold` value of the [`Mixer`]'s [`Envelope`].
+    Hold(f64),
+
+    /// Sent by the UI in [`app`] to modify how much the [`Mixer`]'s [`Envelope`] is shortened for higher notes.
+    KeyScaling(f64),
+
+    /// Sent by the UI in [`app`] to modify how closely an [`Oscillator`] in [`PitchMode::Constant`] follows MIDI
+    /// notes.
+    /// 
+    /// [`PitchMode::Constant`]:    oscillator::PitchMode::Constant
+    KeyTrack{oscillator: usize, key_track: f64},
+
     /// Sent by the UI in [`app`] to modify the master gain of the [`Mixer`].
     /// 
     /// [`Mixer`]:  crate::synth::mixer::Mixer
@@ -52,14 +146,58 @@ pub enum Message {
     /// Sent by the UI in [`app`] to modify the `mode` value of the [`Mixer`].
     MixerMode(mixer::SynthMode),
 
-    /// Sent by [`midi`] to signal a MIDI note-on. Velocity is currently unused.
-    NoteOn{pitch: u8, _velocity: u8},
+    /// Sent by the UI in [`app`] to modify the envelope `amount` of an [`Oscillator`]'s [`Morph`].
+    MorphAmount{oscillator: usize, amount: f64},
+
+    /// Sent by the UI in [`app`] to modify the envelope `attack` of an [`Oscillator`]'s [`Morph`].
+    MorphAttack{oscillator: usize, attack: f64},
+
+    /// Sent by the UI in [`app`] to modify the envelope `decay` of an [`Oscillator`]'s [`Morph`].
+    MorphDecay{oscillator: usize, decay: f64},
+
+    /// Sent by the UI in [`app`] to modify the base `position` of an [`Oscillator`]'s [`Morph`].
+    MorphPosition{oscillator: usize, position: f64},
+
+    /// Sent by the UI in [`app`] to modify the envelope `sustain` of an [`Oscillator`]'s [`Morph`].
+    MorphSustain{oscillator: usize, sustain: f64},
+
+    /// Sent by the UI in [`app`] to modify the [`NoiseColor`] of an [`Oscillator`]'s noise.
+    NoiseColor{oscillator: usize, color: NoiseColor},
+
+    /// Sent by the UI in [`app`] to set whether an [`Oscillator`]'s noise is filtered around its frequency.
+    NoiseKeyTrack{oscillator: usize, key_track: bool},
+
+    /// Sent by the UI in [`app`] to restart an [`Oscillator`]'s random number generator from a new seed.
+    NoiseSeed{oscillator: usize, seed: u64},
+
+    /// Sent by [`midi`] to signal a MIDI note-on. `velocity` `[0..127]` can scale the [`Envelope`]'s attack.
+    NoteOn{pitch: u8, velocity: u8},
 
     /// Sent by [`midi`] to signal a MIDI note-off.
     NoteOff{pitch: u8}, 
 
-    /// Sent by the UI in [`app`] to modify where an [`Oscillator`]'s signal outputs to.
-    Output{oscillator: usize, mode: oscillator::OutputMode},
+    /// Sent by the UI in [`app`] to modify the `octave` detune value of an [`Oscillator`].
+    Octave{oscillator: usize, octave: i32},
+
+    /// Sent by the UI in [`app`] to modify the `attack` time of an [`Oscillator`]'s own envelope.
+    OscAmpAttack{oscillator: usize, attack: f64},
+
+    /// Sent by the UI in [`app`] to modify the `decay` time of an [`Oscillator`]'s own envelope.
+    OscAmpDecay{oscillator: usize, decay: f64},
+
+    /// Sent by the UI in [`app`] to set whether an [`Oscillator`]'s own envelope shapes its output.
+    OscAmpEnabled{oscillator: usize, enabled: bool},
+
+    /// Sent by the UI in [`app`] to modify the `release` time of an [`Oscillator`]'s own envelope.
+    OscAmpRelease{oscillator: usize, release: f64},
+
+    /// Sent by the UI in [`app`] to modify the `sustain` amplitude of an [`Oscillator`]'s own envelope.
+    OscAmpSustain{oscillator: usize, sustain: f64},
+
+    /// Sent by the UI in [`app`] to modify the [`PhaseMode`] of an [`Oscillator`].
+    /// 
+    /// [`PhaseMode`]:  oscillator::PhaseMode
+    PhaseMode{oscillator: usize, mode: oscillator::PhaseMode},
 
     /// Sent by [`midi`] to send a pitch bend to [`Oscillator`]s.
     PitchBend{lsb: u8, msb: u8},
@@ -67,18 +205,93 @@ pub enum Message {
     /// Sent by the UI in [`app`] to modify the `mode` value of an [`Oscillator`].
     PitchMode{oscillator: usize, mode: oscillator::PitchMode},
 
+    /// Sent by the UI in [`app`] to modify the `brightness` of an [`Oscillator`]'s [`Pluck`].
+    PluckBrightness{oscillator: usize, brightness: f64},
+
+    /// Sent by the UI in [`app`] to modify the `damping` of an [`Oscillator`]'s [`Pluck`].
+    PluckDamping{oscillator: usize, damping: f64},
+
+    /// Sent by the UI in [`app`] to modify the pick `position` of an [`Oscillator`]'s [`Pluck`].
+    PluckPosition{oscillator: usize, position: f64},
+
+    /// Sent by the UI in [`app`] to modify the `pulse_width` of an [`Oscillator`].
+    PulseWidth{oscillator: usize, width: f64},
+
+    /// Sent by the UI in [`app`] to modify the `pwm_range` of an [`Oscillator`].
+    PwmRange{oscillator: usize, range: f64},
+
     /// Sent by the UI in [`app`] to modify the `release` value of the [`Mixer`]'s [`Envelope`].
     Release(f64),
 
+    /// Sent by the UI in [`app`] to modify the `release_curve` of the [`Mixer`]'s [`Envelope`].
+    ReleaseCurve(Curve),
+
+    /// Sent by the UI in [`app`] to replace the [`Route`] from one [`Oscillator`] to another in the [`Routing`].
+    /// A `route` of `None` disconnects them.
+    Route{source: usize, target: usize, route: Option<Route>},
+
     /// Sent by the UI in [`app`] to notify [`midi`] to retry the connection to the MIDI device.
     ResetMIDI(),
 
+    /// Sent by the UI in [`app`] to replace the [`Sample`] played by an [`Oscillator`] set to
+    /// [`Waveform::Sample`].
+    Sample{oscillator: usize, sample: Arc<Sample>},
+
+    /// Sent by the UI in [`app`] to modify the `loop_end` of an [`Oscillator`]'s [`Sampler`].
+    SampleLoopEnd{oscillator: usize, end: f64},
+
+    /// Sent by the UI in [`app`] to modify the [`LoopMode`] of an [`Oscillator`]'s [`Sampler`].
+    SampleLoopMode{oscillator: usize, mode: LoopMode},
+
+    /// Sent by the UI in [`app`] to modify the `loop_start` of an [`Oscillator`]'s [`Sampler`].
+    SampleLoopStart{oscillator: usize, start: f64},
+
+    /// Sent by the UI in [`app`] to modify the `root` note of an [`Oscillator`]'s [`Sampler`].
+    SampleRoot{oscillator: usize, root: u8},
+
+    /// Sent by the UI in [`app`] to modify the `start` offset of an [`Oscillator`]'s [`Sampler`].
+    SampleStart{oscillator: usize, start: f64},
+
+    /// Sent by the UI in [`app`] to replace the [`Stage`]s of the [`Mixer`]'s [`MultiStage`] envelope, along with
+    /// its sustain stage and loop.
+    Stages{stages: Vec<Stage>, sustain: Option<usize>, looping: Option<(usize, usize)>},
+
+    /// Sent by the UI in [`app`] to modify the `start_phase` (in degrees) of an [`Oscillator`].
+    StartPhase{oscillator: usize, phase: f64},
+
     /// Sent by the UI in [`app`] to modify the `sustain` value of the [`Mixer`]'s [`Envelope`].
     Sustain(f64),
 
     /// Sent to inform various tasks to shutdown.
     Quit(),
 
+    /// Sent by the UI in [`app`] to replace the [`Tuning`] used by every [`Oscillator`].
+    Tuning(Arc<Tuning>),
+
+    /// Sent by [`midi`] when a MIDI Tuning Standard message retunes `notes` (each a MIDI note and its new
+    /// frequency in Hz) in every [`Oscillator`]'s [`Tuning`].
+    TuningChange{notes: Vec<(u8, f64)>},
+
+    /// Sent by the UI in [`app`] to modify the number of voices in an [`Oscillator`]'s [`Unison`].
+    Unison{oscillator: usize, voices: usize},
+
+    /// Sent by the UI in [`app`] to modify the [`DetuneCurve`] of an [`Oscillator`]'s [`Unison`].
+    UnisonCurve{oscillator: usize, curve: DetuneCurve},
+
+    /// Sent by the UI in [`app`] to modify the `detune` value of an [`Oscillator`]'s [`Unison`].
+    UnisonDetune{oscillator: usize, detune: f64},
+
+    /// Sent by the UI in [`app`] to modify the stereo `spread` of an [`Oscillator`]'s [`Unison`].
+    UnisonSpread{oscillator: usize, spread: f64},
+
+    /// Sent by the UI in [`app`] to modify how much the attack of the [`Mixer`]'s [`Envelope`] is shortened for
+    /// harder notes.
+    VelocityScaling(f64),
+
     /// Sent by the UI in [`app`] to modify the [`Waveform`] of an [`Oscillator`].
     Waveform{oscillator: usize, waveform: Waveform},
+
+    /// Sent by the UI in [`app`] to replace the [`Wavetable`] played by an [`Oscillator`] set to
+    /// [`Waveform::Custom`].
+    Wavetable{oscillator: usize, wavetable: Arc<Wavetable>},
 }
\ No newline at end of file
@@ -1,24 +1,46 @@
-use std::error::Error;
+use std::{
+    error::Error,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
-use midi_control::MidiMessage;
+use midi_control::{message::SysExType, MidiMessage, SysExEvent};
 use midir::{MidiInput, MidiInputConnection};
-use tokio::sync::broadcast::Sender;
+use tokio::{sync::broadcast::Sender, time::Interval};
 
-use crate::message::Message;
+use crate::{arp::Arpeggiator, message::Message};
 
-type Connection = MidiInputConnection<Sender<Message>>;
+type Connection = MidiInputConnection<(Sender<Message>, Arc<Mutex<Arpeggiator>>)>;
+
+/// How often the [`Arpeggiator`] is advanced while it's enabled and not synced to MIDI clock.
+const ARP_INTERVAL: Duration = Duration::from_millis(1);
+
+/// The MIDI clock message, sent 24 times per beat.
+const CLOCK: u8 = 0xF8;
+/// The MIDI start message, sent when the clock begins at the start of a sequence.
+const START: u8 = 0xFA;
+/// The MIDI stop message, sent when the clock stops.
+const STOP: u8 = 0xFC;
 
 /// Connects to the first active MIDI device and listens for input, sending appropriate signals to [`synth`].
 /// 
 /// If no MIDI devices are found, the thread will continue running and will make another attempt to connect to
 /// a MIDI device when sent a button press from the UI in [`app`].
 /// 
+/// Notes pass through an [`Arpeggiator`] on their way to [`synth`], which is configured by the UI. While it's
+/// enabled and not following MIDI clock, it's advanced here every [`ARP_INTERVAL`]; otherwise this thread only
+/// wakes for messages.
+/// 
 /// [`app`]:    crate::app
 /// [`synth`]:  crate::synth
 pub async fn listen(tx: Sender<Message>) -> Result<(), Box<dyn Error>> {
     let mut rx = tx.subscribe();
 
-    let mut connection = connect(tx.clone());
+    let arp = Arc::new(Mutex::new(Arpeggiator::new()));
+    let mut connection = connect(tx.clone(), Arc::clone(&arp));
+
+    let mut interval: Option<Interval> = None;
+    let mut last_tick = Instant::now();
 
     loop { tokio::select! {
         Ok(msg) = rx.recv() => {
@@ -33,17 +55,58 @@ pub async fn listen(tx: Sender<Message>) -> Result<(), Box<dyn Error>> {
                     if let Some(connection) = connection.take() {
                         connection.close();
                     }
-                    connection = connect(tx.clone());
+                    connection = connect(tx.clone(), Arc::clone(&arp));
+                }
+                Message::ArpEnabled(enabled) => {
+                    send(&tx, arp.lock().unwrap().set_enabled(enabled))?;
+                    update_interval(&mut interval, &mut last_tick, &arp);
+                }
+                Message::ArpGate(gate) => arp.lock().unwrap().set_gate(gate),
+                Message::ArpLatch(latch) => send(&tx, arp.lock().unwrap().set_latch(latch))?,
+                Message::ArpOctaves(octaves) => arp.lock().unwrap().set_octaves(octaves),
+                Message::ArpPattern(pattern) => arp.lock().unwrap().set_pattern(pattern),
+                Message::ArpRate(rate) => arp.lock().unwrap().set_rate(rate),
+                Message::ArpSync(sync) => {
+                    arp.lock().unwrap().set_sync(sync);
+                    update_interval(&mut interval, &mut last_tick, &arp);
                 }
+                Message::ArpTempo(tempo) => arp.lock().unwrap().set_tempo(tempo),
                 _ => (),
             }
         }
+        _ = tick(&mut interval) => {
+            let now = Instant::now();
+            let messages = arp.lock().unwrap().tick((now - last_tick).as_secs_f64());
+            last_tick = now;
+            send(&tx, messages)?;
+        }
     }}
 
     Ok(())
 }
 
-fn connect(tx: Sender<Message>) -> Option<Connection> {
+/// Starts `interval` (measuring time from now in `last_tick`) if `arp` has just become timed, or stops it if `arp`
+/// no longer is. See [`Arpeggiator::is_timed()`].
+fn update_interval(interval: &mut Option<Interval>, last_tick: &mut Instant, arp: &Mutex<Arpeggiator>) {
+    if !arp.lock().unwrap().is_timed() {
+        *interval = None;
+    } else if interval.is_none() {
+        *interval = Some(tokio::time::interval(ARP_INTERVAL));
+        *last_tick = Instant::now();
+    }
+}
+
+/// Waits for the next tick of `interval`, or forever if it's stopped.
+async fn tick(interval: &mut Option<Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+fn connect(tx: Sender<Message>, arp: Arc<Mutex<Arpeggiator>>) -> Option<Connection> {
     // client_name is currently unused by the midir code, and its intended purpose is unexplained,
     // so for now I'm passing in an empty string:
     let stream = MidiInput::new("").unwrap();
@@ -61,12 +124,12 @@ fn connect(tx: Sender<Message>) -> Option<Connection> {
     match stream.connect(
         port,
         "synth_jxrs_port",
-        |_timestamp, msg, tx| {
-            if let Err(e) = parse_message(msg, tx) {
+        |_timestamp, msg, (tx, arp)| {
+            if let Err(e) = parse_message(msg, tx, arp) {
                 eprintln!("MIDI error: {e}");
             }
         },
-        tx,
+        (tx, arp),
     ) {
         Ok(c) => {
             eprintln!("Successfully connected to MIDI input.");
@@ -81,20 +144,31 @@ fn connect(tx: Sender<Message>) -> Option<Connection> {
 
 /// Communicates to the rest of the program based on received MIDI input.
 /// 
-/// Currently, only `NoteOn` and `NoteOff` events are supported. Other signals do nothing.
-fn parse_message(msg: &[u8], tx: &mut Sender<Message>) -> Result<(), Box<dyn Error>> {
+/// Currently, `NoteOn`, `NoteOff`, and `PitchBend` events are supported, as well as MIDI Tuning Standard SysEx
+/// messages (see [`parse_tuning()`]). Notes are sent through `arp`, which also follows the clock, start, and stop
+/// messages. Other signals do nothing.
+fn parse_message(msg: &[u8], tx: &mut Sender<Message>, arp: &Arc<Mutex<Arpeggiator>>) -> Result<(), Box<dyn Error>> {
+    // system real-time messages are a single byte, which `MidiMessage` doesn't recognize:
+    match msg.first() {
+        Some(&CLOCK) => return send(tx, arp.lock().unwrap().clock()),
+        Some(&START) => {
+            arp.lock().unwrap().start();
+            return Ok(());
+        }
+        Some(&STOP) => return send(tx, arp.lock().unwrap().stop()),
+        Some(0xF8..=0xFF) => return Ok(()),
+        _ => (),
+    }
+
     match MidiMessage::from(msg) {
         MidiMessage::Invalid => {
             return Err("invalid MIDI received".into());
         }
         MidiMessage::NoteOn(_channel, key_event) => {
-            tx.send(Message::NoteOn{
-                pitch: key_event.key,
-                _velocity: key_event.value
-            })?;
+            send(tx, arp.lock().unwrap().note_on(key_event.key, key_event.value))?;
         }
         MidiMessage::NoteOff(_channel, key_event) => {
-            tx.send(Message::NoteOff{pitch: key_event.key})?;
+            send(tx, arp.lock().unwrap().note_off(key_event.key))?;
         }
         MidiMessage::PolyKeyPressure(_, _) => {}
         MidiMessage::ControlChange(_, _) => {}
@@ -103,7 +177,102 @@ fn parse_message(msg: &[u8], tx: &mut Sender<Message>) -> Result<(), Box<dyn Err
         MidiMessage::PitchBend(_channel, lsb, msb) => {
             tx.send(Message::PitchBend{ lsb, msb })?;
         }
-        MidiMessage::SysEx(_) => {}
+        MidiMessage::SysEx(event) => {
+            let notes = parse_tuning(&event)?;
+            if !notes.is_empty() {
+                tx.send(Message::TuningChange{notes})?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Sends each of `messages` from the [`Arpeggiator`] in order.
+fn send(tx: &Sender<Message>, messages: Vec<Message>) -> Result<(), Box<dyn Error>> {
+    for message in messages {
+        tx.send(message)?;
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Decodes a MIDI Tuning Standard SysEx message into the notes it retunes and their new frequencies (in Hz), which
+/// is empty if `event` isn't one.
+/// 
+/// The bulk tuning dump (which retunes every note) and the single note tuning change (with or without a bank
+/// number) are supported. Every tuning program and bank is treated as the synthesizer's one tuning, and the bulk
+/// dump's checksum isn't checked, since many tools that send it calculate it differently.
+fn parse_tuning(event: &SysExEvent) -> Result<Vec<(u8, f64)>, Box<dyn Error>> {
+    match event.get_type() {
+        SysExType::NonRealTime(_, [0x08, 0x01]) => {
+            // program, 16 name bytes, then a frequency for each of the 128 notes in order
+            let data = event.get_data().get(17..17 + 128 * 3).ok_or("truncated MTS bulk tuning dump")?;
+            Ok(data.chunks_exact(3)
+                .enumerate()
+                .filter_map(|(note, bytes)| mts_frequency(bytes).map(|freq| (note as u8, freq)))
+                .collect())
+        }
+        SysExType::RealTime(_, [0x08, 0x02]) => parse_note_changes(event.get_data().get(1..)),
+        SysExType::RealTime(_, [0x08, 0x07]) | SysExType::NonRealTime(_, [0x08, 0x07]) => {
+            parse_note_changes(event.get_data().get(2..))
+        }
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Decodes the note changes of an MTS single note tuning change message, starting from the number of changes.
+fn parse_note_changes(data: Option<&[u8]>) -> Result<Vec<(u8, f64)>, Box<dyn Error>> {
+    let (&count, changes) = data.and_then(|data| data.split_first()).ok_or("truncated MTS note change")?;
+    let changes = changes.get(..count as usize * 4).ok_or("truncated MTS note change")?;
+
+    Ok(changes.chunks_exact(4)
+        .filter_map(|change| mts_frequency(&change[1..]).map(|freq| (change[0] & 0x7F, freq)))
+        .collect())
+}
+
+/// Converts the three bytes MTS uses to describe a frequency -- a MIDI note, then a 14-bit fraction of a
+/// semitone above it -- to Hz. Returns `None` for the reserved value `7F 7F 7F`, which means "no change".
+fn mts_frequency(bytes: &[u8]) -> Option<f64> {
+    if bytes == [0x7F, 0x7F, 0x7F] {
+        return None;
+    }
+
+    let fraction = ((bytes[1] as u16 & 0x7F) << 7 | bytes[2] as u16 & 0x7F) as f64 / 16384.0;
+    let pitch = (bytes[0] & 0x7F) as f64 + fraction;
+    Some(440.0 * f64::powf(2.0, (pitch - 69.0) / 12.0))
+}
+
+#[cfg(test)]
+mod mts_tests {
+    use super::*;
+
+    #[test]
+    fn single_note_changes_are_decoded() {
+        // retune A4 (69) to a quarter tone above A4 (0x2000 / 0x4000 of a semitone above it), and leave C4 (60) alone
+        let msg = [0xF0, 0x7F, 0x7F, 0x08, 0x02, 0x00, 0x02, 69, 69, 0x40, 0x00, 60, 0x7F, 0x7F, 0x7F, 0xF7];
+        let MidiMessage::SysEx(event) = MidiMessage::from(&msg[..]) else { panic!("not a SysEx message") };
+
+        let notes = parse_tuning(&event).unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].0, 69);
+        assert!((notes[0].1 - 440.0 * f64::powf(2.0, 0.5 / 12.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn bulk_dump_retunes_every_note() {
+        // every note shifted up a semitone
+        let mut msg = vec![0xF0, 0x7E, 0x7F, 0x08, 0x01, 0x00];
+        msg.extend(b"Shifted up 1    ");
+        for note in 0..128u8 {
+            msg.extend([note.saturating_add(1).min(127), 0, 0]);
+        }
+        msg.extend([0x00, 0xF7]);
+        let MidiMessage::SysEx(event) = MidiMessage::from(&msg[..]) else { panic!("not a SysEx message") };
+
+        let notes = parse_tuning(&event).unwrap();
+        assert_eq!(notes.len(), 128);
+        assert_eq!(notes[68], (68, 440.0));
+
+        let MidiMessage::SysEx(truncated) = MidiMessage::from(&msg[..100]) else { panic!("not a SysEx message") };
+        assert!(parse_tuning(&truncated).is_err());
+    }
+}
@@ -1,22 +1,52 @@
-use std::{collections::BTreeSet, time::Instant};
+use std::collections::BTreeSet;
+
+use crate::synth;
+
+use super::multistage::MultiStage;
+
+/// The shortest time (in seconds) the envelope can take to move between silence and full amplitude. Faster
+/// changes, such as an instant attack or a jump in `sustain`, are spread over this time to avoid clicks.
+const ANTI_CLICK_RAMP: f64 = 0.002;
+
+/// How sharply [`Curve::Exponential`] and [`Curve::Logarithmic`] bend: the steepest a [`Curve`] can be.
+const CURVE_STEEPNESS: f64 = 5.0;
 
 /// Manages the amplitude/volume of the synthesizer, when set to base amplitude on MIDI input.
+/// 
+/// The [`Envelope`] is advanced by [`next()`] once per sample, so its timing doesn't depend on when the audio
+/// device asks for each buffer.
+/// 
+/// [`next()`]: Amplifier::next()
 pub struct Amplifier {
     /// The set of MIDI pitches `[0..127]` currently held by the MIDI input device.
     active_notes: BTreeSet<u8>,
     /// Contains duration information for changing amplitude throughout a note's lifetime.
     pub adsr: Envelope,
-    /// This field is used to keep track of what amplitude the signal should start at when releasing.
-    last_amplitude: f64,
+    /// The time (in seconds) since the most recent note began, or since the release began if no note is held.
+    elapsed: f64,
+    /// Which envelope shapes the amplitude.
+    pub envelope: AmpEnvelope,
+    /// The MIDI pitch `[0..127]` of the note that most recently restarted the envelope, which scales its times.
+    key: u8,
     /// When legato is `false`, envelopes restart from the beginning when two notes overlap. When legato
     /// is `true`, overlapping notes will continue with the same, uninterrupted envelope.
     legato: bool,
+    /// The amplitude most recently returned by [`next()`](Amplifier::next()).
+    level: f64,
     /// Stores whether there is currently any active note being held.
     note_on: bool,
-    /// If a note is currently held, the `Instant` of when the most recent note began.
-    start_time: Option<Instant>,
-    /// If the envelope is currently in the release phase, the `Instant` of when the last note was released.
-    release_time: Option<Instant>,
+    /// Whether the envelope is currently in the release phase.
+    releasing: bool,
+    /// A [`MultiStage`] envelope, triggered by the same notes as `adsr`, if the `Amplifier` was created with
+    /// [`with_stages()`](Amplifier::with_stages()). It shapes the amplitude instead of `adsr` in
+    /// [`AmpEnvelope::MultiStage`], and is always advanced so that it can modulate the `Oscillator`s.
+    pub stages: Option<MultiStage>,
+    /// The amplitude the envelope was at when the most recent attack or release began, which that stage moves
+    /// from.
+    start_level: f64,
+    /// The MIDI velocity `[0..127]` of the note that most recently restarted the envelope, which scales its
+    /// attack.
+    velocity: u8,
 }
 
 impl Amplifier {
@@ -25,34 +55,48 @@ impl Amplifier {
         Amplifier::new(Envelope::default())
     }
 
+    /// Returns a new `Amplifier` using the default [`Envelope`], with a [`MultiStage`] envelope in `stages`.
+    /// 
+    /// Only the [`Mixer`](super::mixer::Mixer)'s `Amplifier` has one, so the `Oscillator`s' own amplifiers don't
+    /// spend time advancing an envelope nothing uses.
+    pub fn with_stages() -> Amplifier {
+        Amplifier { stages: Some(MultiStage::new()), ..Amplifier::default() }
+    }
+
     /// Returns a new 'Amplifier', accepting any [`Envelope`] into the `adsr` parameter.
     pub fn new(adsr: Envelope) -> Amplifier {
         Amplifier {
             active_notes: BTreeSet::new(),
             adsr,
-            last_amplitude: 0.0,
+            elapsed: 0.0,
+            envelope: AmpEnvelope::Dahdsr,
+            key: 60,
             legato: false,
+            level: 0.0,
             note_on: false,
-            start_time: None,
-            release_time: None,
+            releasing: false,
+            stages: None,
+            start_level: 0.0,
+            velocity: 0,
         }
     }
 
     /// Sends the MIDI "NoteOn" signal to `self`.
     /// 
     /// If another note is already held, this function will add the new note to the `Amplifier`'s internal
-    /// set of active notes. This function also restarts the [`Envelope`] from the beginning of the attack,
-    /// if `self.legato` is `false`.
-    pub fn note_on(&mut self, pitch: u8) {
-        self.note_on = true;
-        if self.start_time.is_none() { // if no other note is currently playing
+    /// set of active notes. This function also restarts the [`Envelope`] from the beginning of its delay,
+    /// if `self.legato` is `false`, with its times scaled by `pitch` and `velocity`. The attack rises from the
+    /// envelope's current amplitude, so a note played during another's release doesn't drop to 0 first.
+    pub fn note_on(&mut self, pitch: u8, velocity: u8) {
+        if !self.note_on { // if no other note is currently playing
+            self.note_on = true;
             self.active_notes.insert(pitch);
-            self.start_time = Some(Instant::now());
-            self.release_time.take();
+            self.trigger(pitch, velocity);
+            self.releasing = false;
         } else if !self.active_notes.contains(&pitch) { // if another note is playing but not this one
             self.active_notes.insert(pitch);
             if !self.legato {
-                self.start_time = Some(Instant::now());
+                self.trigger(pitch, velocity);
             }
         }
     }
@@ -60,99 +104,421 @@ impl Amplifier {
     /// Sends the MIDI "NoteOff" signal to `self`.
     /// 
     /// The note affected by the signal is removed from the `Amplifier`'s internal set of active notes.
-    /// If that was the only actively held note, the [`Envelope`] releases.
+    /// If that was the only actively held note, the [`Envelope`] releases from its current amplitude.
     pub fn note_off(&mut self, pitch: u8) {
-        if self.release_time.is_none() && self.active_notes.len() <= 1 {
+        if self.note_on && self.active_notes.len() <= 1 {
             self.note_on = false;
-            self.start_time.take();
-            self.release_time = Some(Instant::now());
+            self.releasing = true;
+            if let Some(stages) = &mut self.stages {
+                stages.release();
+            }
+            self.restart();
         }
         self.active_notes.remove(&pitch);
     }
 
+    /// Restarts the envelopes for a note at `pitch` and `velocity`.
+    fn trigger(&mut self, pitch: u8, velocity: u8) {
+        self.key = pitch;
+        self.velocity = velocity;
+        if let Some(stages) = &mut self.stages {
+            stages.trigger();
+        }
+        self.restart();
+    }
+
+    /// Starts a new delay or release from the envelope's current amplitude.
+    fn restart(&mut self) {
+        self.elapsed = 0.0;
+        self.start_level = self.level;
+    }
+
     /// Multiplies `sample_in` by an amplitude modifier based on `self`'s [`Envelope`] and the history of
-    /// MIDI signals sent to this `Amplifier`.
+    /// MIDI signals sent to this `Amplifier`, advancing the envelope by one sample.
+    pub fn calc(&mut self, sample_in: f64) -> f64 {
+        sample_in * unsafe { self.next(1.0 / synth::SAMPLE_RATE) }
+    }
+
+    /// Returns the current amplitude of the [`Envelope`], then advances it by `step` seconds.
     /// 
-    /// The growth and decay of amplitude during `attack`, `decay`, and `release` are linear. Note also that
-    /// if `self.adsr.decay > 0.0`, the starting amplitude, or the amplitude reached by the end of the `attack`
-    /// duration if there is one, is 1.0, allowing for the amplitude to decrease towards the `sustain` amplitude.
+    /// The envelope holds its amplitude for `delay`, then attacks, holds its peak for `hold`, and decays to
+    /// `sustain`. Each of `attack`, `decay`, and `release` follows its own [`Curve`], and their times are scaled
+    /// as described by [`Envelope::times()`]. Note that if `self.adsr.decay > 0.0`, the amplitude reached by the
+    /// end of the `attack` duration is 1.0, allowing for the amplitude to decrease towards the `sustain`
+    /// amplitude. The attack and release each start from whatever amplitude the envelope had reached, so
+    /// retriggering or releasing a note part-way through a stage doesn't jump. Any change faster than
+    /// [`ANTI_CLICK_RAMP`] allows is slowed to that rate.
     /// 
     /// If no notes are held, and a duration greater than 'self.adsr.release' has passed, this function
     /// returns 0.0.
-    pub fn calc(&mut self, sample_in: f64) -> f64 {
-        let amplitude = if self.note_on {
-            let since_attack = Instant::now()
-                .duration_since(self.start_time
-                .expect("Start time shouldn't be None when note_on is true"))
-                .as_secs_f64();
-
-            // The Amplifier's last_amplitude field is used to keep track of the last generated
-            // amplitude of the "on" phase of the note's lifetime. This is used to prevent the
-            // note from jumping up to sustain amplitude if it wasn't reached before the note ended.
-            self.last_amplitude = if since_attack <= self.adsr.attack { // attack
-                if self.adsr.decay > 0.0 {
-                    // attack towards 1.0 so decay can decrease to sustain amplitude
-                    since_attack / self.adsr.attack
-                } else {
-                    // attack towards sustain amplitude
-                    self.adsr.sustain * since_attack / self.adsr.attack
-                }
-            } else if since_attack > self.adsr.attack + self.adsr.decay { // sustain
-                self.adsr.sustain
-            } else { // decay
-                1.0 - ((1.0 - self.adsr.sustain) * (since_attack - self.adsr.attack)) / self.adsr.decay
-            };
-
-            self.last_amplitude
-
-        } else if let Some(release_time) = self.release_time {
-            let since_release = Instant::now()
-                .duration_since(release_time)
-                .as_secs_f64();
+    /// 
+    /// `self.stages` (if there is one) is advanced at the same time, and in [`AmpEnvelope::MultiStage`] its level
+    /// is used instead (with the same anti-click ramp). Without one, that mode is silent.
+    pub fn next(&mut self, step: f64) -> f64 {
+        let adsr = &self.adsr;
+        let (attack, decay, release) = adsr.times(self.key, self.velocity);
+        // attack towards 1.0 if decay can decrease to sustain amplitude, or else towards sustain:
+        let peak = if decay > 0.0 { 1.0 } else { adsr.sustain };
+        let since_delay = self.elapsed - adsr.delay;
 
-            if since_release >= self.adsr.release {
-                self.release_time.take();
-                0.0
+        let target = if self.note_on {
+            if since_delay < 0.0 { // delay
+                self.start_level
+            } else if since_delay < attack { // attack
+                self.start_level + (peak - self.start_level) * adsr.attack_curve.apply(since_delay / attack)
+            } else if since_delay < attack + adsr.hold { // hold
+                peak
+            } else if since_delay < attack + adsr.hold + decay { // decay
+                let progress = (since_delay - attack - adsr.hold) / decay;
+                1.0 - (1.0 - adsr.sustain) * adsr.decay_curve.apply(progress)
+            } else { // sustain
+                adsr.sustain
+            }
+        } else if self.releasing {
+            if self.elapsed < release {
+                self.start_level * (1.0 - adsr.release_curve.apply(self.elapsed / release))
             } else {
-                self.last_amplitude - (since_release / self.adsr.release)
+                self.releasing = false;
+                0.0
             }
         } else {
             0.0
         };
+        let stages = self.stages.as_mut().map_or(0.0, |stages| stages.next(step));
+        let target = match self.envelope {
+            AmpEnvelope::Dahdsr => target,
+            AmpEnvelope::MultiStage => stages,
+        };
 
-        sample_in * amplitude
+        let max_change = step / ANTI_CLICK_RAMP;
+        self.level += (target - self.level).clamp(-max_change, max_change);
+        self.elapsed += step;
+
+        self.level
     }
 }
 
+/// Describes which envelope an [`Amplifier`] shapes its amplitude with.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum AmpEnvelope {
+    /// The [`Envelope`] in `adsr`.
+    Dahdsr,
+    /// The [`MultiStage`] envelope in `stages`.
+    MultiStage,
+}
+
+/// The shape of a segment of an [`Envelope`], as it moves from one amplitude to another.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Curve {
+    /// Changes at a constant rate.
+    Linear,
+    /// Changes quickly at first, then slows as it nears its target, like the charge or discharge of an analog
+    /// envelope. Released notes fade away naturally, instead of cutting off at the end.
+    Exponential,
+    /// Changes slowly at first, then speeds up as it nears its target: the opposite of `Exponential`.
+    Logarithmic,
+    /// A `tension` `[-1..1]` between the other curves: 1 is `Exponential`, 0 is `Linear`, and -1 is
+    /// `Logarithmic`.
+    Tension(f64),
+}
+
+impl Curve {
+    /// Returns how far `[0..1]` along a segment of this shape the amplitude has moved, at `progress` `[0..1]`
+    /// through the segment's duration.
+    pub fn apply(&self, progress: f64) -> f64 {
+        let tension = match *self {
+            Curve::Linear => 0.0,
+            Curve::Exponential => 1.0,
+            Curve::Logarithmic => -1.0,
+            Curve::Tension(tension) => tension.clamp(-1.0, 1.0),
+        };
+        let progress = progress.clamp(0.0, 1.0);
+
+        let k = tension * CURVE_STEEPNESS;
+        if k.abs() < 1e-6 {
+            progress
+        } else {
+            (1.0 - f64::exp(-k * progress)) / (1.0 - f64::exp(-k))
+        }
+    }
+}
 
-/// An ADSR amplitude envelope for use by an [`Amplifier`].
+/// A DAHDSR (delay, attack, hold, decay, sustain, release) amplitude envelope for use by an [`Amplifier`].
 pub struct Envelope {
     /// The time (in seconds) the sound takes to reach its peak amplitude after beginning.
     pub attack: f64,
+    /// The [`Curve`] the amplitude follows during the attack.
+    pub attack_curve: Curve,
     /// The time (in seconds) the sound takes to decay to its sustain amplitude, after the attack.
     pub decay: f64,
+    /// The [`Curve`] the amplitude follows during the decay.
+    pub decay_curve: Curve,
+    /// The time (in seconds) between a note starting and the attack beginning.
+    pub delay: f64,
+    /// The time (in seconds) the sound holds at its peak amplitude, between the attack and decay.
+    pub hold: f64,
+    /// How much `[0..1]` higher notes shorten the decay and release. At 1, they halve with each octave above
+    /// middle C (and double with each octave below).
+    key_scaling: f64,
     /// The amplitude `[0..1]` the sound should hold at, after the attack and decay times lapse. 
     sustain: f64,
     /// The time (in seconds) the sound takes from when the note stops to reach an amplitude of 0.
     pub release: f64,
+    /// The [`Curve`] the amplitude follows during the release.
+    pub release_curve: Curve,
+    /// How much `[0..1]` harder notes shorten the attack. At 1, a note at full velocity attacks instantly.
+    velocity_scaling: f64,
 }
 
 impl Envelope {
-    /// Returns a new `Envelope` with the default values: instant `attack`, `decay`, and `release`
-    /// times, and a `sustain` amplitude of 1.0.
+    /// Returns a new `Envelope` with the default values: no `delay` or `hold`, instant, linear `attack`,
+    /// `decay`, and `release` times with no scaling, and a `sustain` amplitude of 1.0.
     pub fn default() -> Envelope {
         Envelope {
             attack: 0.0,
+            attack_curve: Curve::Linear,
             decay: 0.0,
+            decay_curve: Curve::Linear,
+            delay: 0.0,
+            hold: 0.0,
+            key_scaling: 0.0,
             sustain: 1.0,
             release: 0.0,
+            release_curve: Curve::Linear,
+            velocity_scaling: 0.0,
         }
     }
 
+    /// Restricts `key_scaling` to the range `[0..1]`, then replaces `self.key_scaling`.
+    pub fn set_key_scaling(&mut self, key_scaling: f64) {
+        self.key_scaling = key_scaling.clamp(0.0, 1.0);
+    }
+
     /// Replaces the `sustain` field of `self` with the given value.
     /// 
     /// The input value is constrained to the range `[0..1]`.
     pub fn set_sustain(&mut self, sustain: f64) {
-        self.sustain = sustain.min(1.0).max(0.0);
+        self.sustain = sustain.clamp(0.0, 1.0);
+    }
+
+    /// Restricts `velocity_scaling` to the range `[0..1]`, then replaces `self.velocity_scaling`.
+    pub fn set_velocity_scaling(&mut self, velocity_scaling: f64) {
+        self.velocity_scaling = velocity_scaling.clamp(0.0, 1.0);
+    }
+
+    /// Returns the `attack`, `decay`, and `release` times (in seconds) for a note at MIDI pitch `key` and
+    /// `velocity` `[0..127]`, scaled by `self.key_scaling` and `self.velocity_scaling`.
+    pub fn times(&self, key: u8, velocity: u8) -> (f64, f64, f64) {
+        let key_factor = f64::powf(2.0, -self.key_scaling * (key as f64 - 60.0) / 12.0);
+        let velocity_factor = 1.0 - self.velocity_scaling * velocity.min(127) as f64 / 127.0;
+
+        (self.attack * velocity_factor, self.decay * key_factor, self.release * key_factor)
+    }
+}
+
+#[cfg(test)]
+mod envelope_tests {
+    use super::*;
+
+    /// Renders `length` samples of `amp`'s envelope at 1kHz.
+    fn render(amp: &mut Amplifier, length: usize) -> Vec<f64> {
+        (0..length).map(|_| amp.next(0.001)).collect()
+    }
+
+    #[test]
+    fn curves_meet_at_their_ends() {
+        for curve in [Curve::Linear, Curve::Exponential, Curve::Logarithmic, Curve::Tension(0.3)] {
+            assert_eq!(curve.apply(0.0), 0.0);
+            assert!((curve.apply(1.0) - 1.0).abs() < 1e-12);
+        }
+
+        assert_eq!(Curve::Linear.apply(0.25), 0.25);
+        assert!(Curve::Exponential.apply(0.25) > Curve::Tension(0.5).apply(0.25));
+        assert!(Curve::Tension(0.5).apply(0.25) > 0.25);
+        assert!(Curve::Logarithmic.apply(0.25) < 0.25);
+        assert_eq!(Curve::Tension(1.0).apply(0.4), Curve::Exponential.apply(0.4));
+    }
+
+    #[test]
+    fn segments_follow_their_curves() {
+        let mut amp = Amplifier::default();
+        amp.adsr.attack = 0.01;
+        amp.adsr.attack_curve = Curve::Logarithmic;
+        amp.adsr.decay = 0.01;
+        amp.adsr.decay_curve = Curve::Exponential;
+        amp.adsr.set_sustain(0.5);
+
+        amp.note_on(60, 127);
+        let levels = render(&mut amp, 30);
+        assert_eq!(levels[0], 0.0);
+        assert!((levels[5] - Curve::Logarithmic.apply(0.5)).abs() < 1e-9);
+        assert!((levels[15] - (1.0 - 0.5 * Curve::Exponential.apply(0.5))).abs() < 1e-9);
+        assert_eq!(levels[25], 0.5);
+    }
+
+    #[test]
+    fn release_starts_from_current_level() {
+        let mut amp = Amplifier::default();
+        amp.adsr.attack = 0.1;
+        amp.adsr.release = 0.01;
+        amp.adsr.release_curve = Curve::Exponential;
+
+        // released a quarter of the way through the attack:
+        amp.note_on(60, 127);
+        let before = *render(&mut amp, 26).last().unwrap();
+        amp.note_off(60);
+        let release = render(&mut amp, 12);
+
+        assert!((release[0] - before).abs() < 1e-9);
+        assert!(release.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert!(release.iter().all(|&level| level >= 0.0));
+        assert_eq!(release[10..], [0.0, 0.0]);
+    }
+
+    #[test]
+    fn only_amplifiers_with_stages_advance_them() {
+        let mut mixer_amp = Amplifier::with_stages();
+        let mut osc_amp = Amplifier::default();
+        assert!(osc_amp.stages.is_none());
+
+        // the Mixer's envelope moves under the Dahdsr envelope too, since it modulates the Oscillators
+        mixer_amp.note_on(60, 127);
+        osc_amp.note_on(60, 127);
+        render(&mut mixer_amp, 10);
+        assert!(mixer_amp.stages.as_ref().is_some_and(|stages| stages.level() > 0.0));
+
+        osc_amp.envelope = AmpEnvelope::MultiStage;
+        assert!(render(&mut osc_amp, 10).iter().all(|&level| level == 0.0));
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod continuity_tests {
+    use super::*;
+
+    /// The largest change allowed between two samples at 48kHz: a little more than [`ANTI_CLICK_RAMP`] allows.
+    const MAX_JUMP: f64 = 0.011;
+
+    /// A note event at a time (in milliseconds) within a sequence: `Some(pitch)` starts a note and `None`
+    /// releases every held note.
+    type Event = (usize, Option<u8>);
+
+    /// Plays `events` through `amp` for `length` milliseconds at 48kHz, returning the largest sample-to-sample
+    /// change in amplitude.
+    fn largest_jump(amp: &mut Amplifier, events: &[Event], length: usize) -> f64 {
+        let mut held = Vec::new();
+        let mut previous = amp.level;
+        let mut largest: f64 = 0.0;
+
+        for i in 0..length * 48 {
+            for &(_, event) in events.iter().filter(|(time, _)| time * 48 == i) {
+                match event {
+                    Some(pitch) => {
+                        amp.note_on(pitch, 127);
+                        held.push(pitch);
+                    }
+                    None => held.drain(..).for_each(|pitch| amp.note_off(pitch)),
+                }
+            }
+            let level = amp.next(1.0 / 48000.0);
+            largest = largest.max((level - previous).abs());
+            previous = level;
+        }
+
+        largest
+    }
+
+    fn envelope(attack: f64, decay: f64, sustain: f64, release: f64) -> Amplifier {
+        let mut amp = Amplifier::default();
+        amp.adsr.attack = attack;
+        amp.adsr.decay = decay;
+        amp.adsr.set_sustain(sustain);
+        amp.adsr.release = release;
+        amp
+    }
+
+    #[test]
+    fn retrigger_during_release_rises_from_current_level() {
+        let mut amp = envelope(0.05, 0.0, 1.0, 0.2);
+        let events = [(0, Some(60)), (100, None), (150, Some(62)), (160, None), (170, Some(64)), (300, None)];
+
+        assert!(largest_jump(&mut amp, &events, 600) <= MAX_JUMP);
+    }
+
+    #[test]
+    fn overlapping_notes_restart_without_jumps() {
+        let mut amp = envelope(0.02, 0.1, 0.3, 0.05);
+        let events = [(0, Some(60)), (10, Some(62)), (50, Some(64)), (200, None), (210, Some(65)), (220, None)];
+
+        assert!(largest_jump(&mut amp, &events, 400) <= MAX_JUMP);
+    }
+
+    #[test]
+    fn instant_envelopes_are_ramped() {
+        let mut amp = envelope(0.0, 0.0, 1.0, 0.0);
+        let events = [(0, Some(60)), (20, None), (21, Some(60)), (40, None)];
+
+        assert!(largest_jump(&mut amp, &events, 100) <= MAX_JUMP);
+
+        // the ramp still reaches full amplitude, and silence, within ANTI_CLICK_RAMP:
+        let mut amp = envelope(0.0, 0.0, 1.0, 0.0);
+        amp.note_on(60, 127);
+        let levels: Vec<f64> = (0..200).map(|_| amp.next(1.0 / 48000.0)).collect();
+        assert_eq!(levels[100], 1.0);
+        amp.note_off(60);
+        let levels: Vec<f64> = (0..200).map(|_| amp.next(1.0 / 48000.0)).collect();
+        assert_eq!(levels[100], 0.0);
+    }
+
+    #[test]
+    fn sustain_changes_are_ramped() {
+        let mut amp = envelope(0.0, 0.0, 1.0, 0.0);
+        amp.note_on(60, 127);
+        (0..480).for_each(|_| { amp.next(1.0 / 48000.0); });
+
+        amp.adsr.set_sustain(0.0);
+        assert!(largest_jump(&mut amp, &[], 10) <= MAX_JUMP);
+    }
+}
+
+#[cfg(test)]
+mod dahdsr_tests {
+    use super::*;
+
+    #[test]
+    fn delay_and_hold_surround_the_attack() {
+        let mut amp = Amplifier::default();
+        amp.adsr.delay = 0.01;
+        amp.adsr.attack = 0.01;
+        amp.adsr.hold = 0.01;
+        amp.adsr.decay = 0.01;
+        amp.adsr.set_sustain(0.5);
+
+        amp.note_on(60, 100);
+        let levels: Vec<f64> = (0..50).map(|_| amp.next(0.001)).collect();
+        assert!(levels[..10].iter().all(|&level| level == 0.0));
+        assert!((levels[15] - 0.5).abs() < 1e-9);
+        assert!(levels[20..30].iter().all(|&level| level == 1.0));
+        assert!((levels[35] - 0.75).abs() < 1e-9);
+        assert_eq!(levels[45], 0.5);
+    }
+
+    #[test]
+    fn times_scale_with_key_and_velocity() {
+        let mut envelope = Envelope::default();
+        envelope.attack = 1.0;
+        envelope.decay = 1.0;
+        envelope.release = 2.0;
+        assert_eq!(envelope.times(84, 127), (1.0, 1.0, 2.0));
+
+        envelope.set_key_scaling(1.0);
+        envelope.set_velocity_scaling(0.5);
+        assert_eq!(envelope.times(72, 127), (0.5, 0.5, 1.0));
+        assert_eq!(envelope.times(48, 0), (1.0, 2.0, 4.0));
+
+        // half the key scaling lengthens times by half as many octaves:
+        envelope.set_key_scaling(0.5);
+        let (_, decay, _) = envelope.times(36, 0);
+        assert!((decay - 2.0).abs() < 1e-12);
+    }
+}
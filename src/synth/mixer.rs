@@ -18,21 +18,22 @@ impl Mixer {
     pub fn new() -> Mixer {
         Mixer {
             master_gain: 1.0,
-            amp: Amplifier::default(),
+            amp: Amplifier::with_stages(),
             mode: SynthMode::MIDI,
         }
     }
 
-    /// If `self.mode` is [`SynthMode::MIDI`], calls [`Amplifier::calc()`] on `sample_in` before multiplying
-    /// by `self.master_gain`.
+    /// If `self.mode` is [`SynthMode::MIDI`], calls [`Amplifier::calc()`] to apply the envelope to both channels
+    /// of `sample_in` before multiplying by `self.master_gain`.
     /// 
-    /// Otherwise, just multiplies `sample_in` by `self.master_gain`.
-    pub fn calc(&mut self, sample_in: f64) -> f64 {
-        if self.mode == SynthMode::MIDI {
-            self.amp.calc(sample_in) * self.master_gain
-        } else {
-            sample_in * self.master_gain
-        }
+    /// Otherwise, just multiplies `sample_in` by `self.master_gain`. The envelopes are still advanced, so that
+    /// they can modulate the `Oscillator`s.
+    pub fn calc(&mut self, sample_in: (f64, f64)) -> (f64, f64) {
+        // the envelope is calculated once and shared between channels:
+        let enveloped = self.amp.calc(self.master_gain);
+        let gain = if self.mode == SynthMode::MIDI { enveloped } else { self.master_gain };
+
+        (sample_in.0 * gain, sample_in.1 * gain)
     }
 
     /// Modifies the `master_gain` property of `self`.
@@ -50,6 +51,7 @@ impl Mixer {
 }
 
 /// Determines whether the envelope of the synth should follow MIDI signals.
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone,Debug,PartialEq)]
 pub enum SynthMode {
     Constant,
@@ -6,14 +6,19 @@ use std::{
 use crate::*;
 use message::Message;
 use mixer::Mixer;
+use multistage::MultiStage;
 use osc::oscillator::{PitchMode, Oscillator};
+use routing::{Algorithm, ModKind, Route, Routing};
 use tokio::sync::broadcast::Sender;
 
 use cpal::{traits::{DeviceTrait, HostTrait, StreamTrait}, FromSample, SizedSample, Stream};
 
 pub mod amp;
 pub mod mixer;
+pub mod multistage;
 pub mod osc;
+pub mod routing;
+pub mod tuning;
 
 /// The number of [`Oscillator`]s the synthesizer should have. Currently, this is a convenience identifier
 /// for a value that shouldn't be edited. In order for this number to have the power to quickly alter the
@@ -21,9 +26,12 @@ pub mod osc;
 /// the UI for an arbitrary amount of oscillators.
 pub const NUM_OSCS: usize = 4;
 
-/// A table of MIDI pitch values `[0..127]` and their corresponding frequencies in Hz.
+/// A table of MIDI pitch values `[0..127]` and their corresponding frequencies in Hz, in 12-tone equal
+/// temperament with A4 at 440Hz. This is the default [`Tuning`].
 /// 
 /// This array is referenced in [`build()`] to ensure its initialization at startup.
+/// 
+/// [`Tuning`]: tuning::Tuning
 pub static MIDI_TO_HZ: LazyLock<[f64; 128]> = LazyLock::new(|| {
     array::from_fn(|i| {
         let val = f64::powf(2.0, (i as f64 - 69.0) / 12.0);
@@ -104,13 +112,18 @@ where
     let mixer = Arc::new(Mutex::new(Mixer::new()));
     let stream_mixer = Arc::clone(&mixer);
 
+    // initialize routing
+    let routing = Arc::new(Mutex::new(Routing::new()));
+    let stream_routing = Arc::clone(&routing);
+
     let stream = device.build_output_stream(
         config,
         move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
             // can't move directly into fn type expected by build_output_stream:
             let stream_mixer = Arc::clone(&stream_mixer);
             let stream_oscs = Arc::clone(&stream_oscs);
-            output(stream_mixer, data, channels, stream_oscs)
+            let stream_routing = Arc::clone(&stream_routing);
+            output(stream_mixer, data, channels, stream_oscs, stream_routing)
         },
         |err| eprintln!("Stream error: {}", err),
         None,
@@ -126,7 +139,7 @@ where
         Ok(msg) = rx.recv() => {
             match msg {
                 Message::Quit() => return Ok(()),
-                _ => parse_message(msg, Arc::clone(&mixer), Arc::clone(&oscs)),
+                _ => parse_message(msg, Arc::clone(&mixer), Arc::clone(&oscs), Arc::clone(&routing)),
             }
         }
         else => { }
@@ -135,51 +148,153 @@ where
 
 /// Callback for `cpal::Stream` used by [`run()`].
 /// 
-/// This function generates and outputs to the audio device each sample.
+/// This function generates and outputs to the audio device each sample, using [`render()`] to calculate the
+/// [`Oscillator`]s before passing the result through the [`Mixer`].
 fn output<T>(
     mixer: Arc<Mutex<Mixer>>,
     output: &mut [T],
     channels: usize,
     oscs: Arc<Vec<Mutex<Oscillator>>>,
+    routing: Arc<Mutex<Routing>>,
 )
 where
     T: SizedSample + FromSample<f64> + Display
 {
+    let mut routing = routing.lock().unwrap();
+
     for frame in output.chunks_mut(channels) {
-        let mut amps: [f64; NUM_OSCS] = [0.0; NUM_OSCS];
+        // the oscillators are modulated by the envelope's level from the previous sample, since it's advanced along
+        // with the amplitude after they're calculated:
+        let envelope = mixer.lock().unwrap().amp.stages.as_ref().map_or(0.0, MultiStage::level);
+        let (left, right) = render(&oscs, &mut routing, envelope);
+        let (left, right) = mixer.lock().unwrap().calc((0.25 * left, 0.25 * right));
 
-        for i in 0..NUM_OSCS {
-            let mut lock = oscs[i].lock().unwrap();
-            match lock.get_output_mode() {
-                osc::oscillator::OutputMode::Master => {
-                    amps[i] = lock.calc();
-                }
-                osc::oscillator::OutputMode::Osc(j) => {
-                    oscs[j].lock().unwrap().fm_sample_in(i, lock.calc());
-                }
+        // mono devices get both channels mixed together, otherwise channels alternate left and right
+        if channels == 1 {
+            frame[0] = T::from_sample(0.5 * (left + right));
+        } else {
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                *sample = T::from_sample(if channel % 2 == 0 { left } else { right });
             }
         }
+    }
+}
 
-        let mut value: f64 = 0.0;
-        for amp in amps {
-            value += amp;
+/// Calculates one sample from every [`Oscillator`], returning the sum of those sent to the [`Mixer`].
+/// 
+/// `Oscillator`s are calculated in the order given by [`Routing::order()`], and each result is passed along to
+/// its targets immediately. Routes that are part of a cycle are instead fed the source's output from the previous
+/// sample, just before their target is calculated. Every `Oscillator` is given the latest level of the
+/// [`MultiStage`] `envelope` first.
+/// 
+/// [`MultiStage`]: multistage::MultiStage
+fn render(oscs: &[Mutex<Oscillator>], routing: &mut Routing, envelope: f64) -> (f64, f64) {
+    let mut left = 0.0;
+    let mut right = 0.0;
+    let mut history = [(0.0, None); NUM_OSCS];
+
+    for i in routing.order() {
+        let mut lock = oscs[i].lock().unwrap();
+        lock.env_sample_in(envelope);
+
+        for (source, route) in routing.delayed_into(i) {
+            let (sample, sync) = routing.history(source);
+            modulate(&mut lock, source, route, sample, sync);
         }
-        let value = T::from_sample(
-            mixer.lock().unwrap().calc(0.25 * value)
-        );
 
-        for sample in frame.iter_mut() {
-            *sample = value;
+        let (osc_left, osc_right) = lock.calc();
+        if routing.master(i) {
+            left += osc_left;
+            right += osc_right;
         }
+
+        let sample = 0.5 * (osc_left + osc_right);
+        let sync = lock.sync_out();
+        for (j, route) in routing.routes_from(i) {
+            modulate(&mut oscs[j].lock().unwrap(), i, route, sample, sync);
+        }
+
+        history[i] = (sample, sync);
+    }
+
+    routing.set_history(history);
+
+    (left, right)
+}
+
+/// Passes the latest `sample` (and `sync`, from [`Oscillator::sync_out()`]) of the `Oscillator` at `source`
+/// along to `target`, as described by `route`.
+fn modulate(target: &mut Oscillator, source: usize, route: Route, sample: f64, sync: Option<f64>) {
+    match route.kind {
+        ModKind::Fm => target.fm_sample_in(source, route.apply(sample)),
+        ModKind::Pwm => target.pwm_sample_in(source, route.apply(sample)),
+        ModKind::Sync => target.sync_sample_in(source, sync),
+        ModKind::Am => target.am_sample_in(source, route.apply(sample)),
+        ModKind::Ring => target.ring_sample_in(source, route.apply(sample)),
+    }
+}
+
+/// Replaces every [`Route`] in `routing` with those described by `algorithm`, connected as FM at full depth.
+fn set_algorithm(oscs: &[Mutex<Oscillator>], routing: &mut Routing, algorithm: Algorithm) {
+    for (source, master) in algorithm.master().into_iter().enumerate() {
+        routing.set_master(source, master);
+
+        for target in 0..NUM_OSCS {
+            let route = if algorithm.routes().contains(&(source, target)) {
+                Some(Route { kind: ModKind::Fm, depth: 1.0 })
+            } else {
+                None
+            };
+            set_route(oscs, routing, source, target, route);
+        }
+    }
+}
+
+/// Replaces the [`Route`] from `source` to `target` in `routing`, and connects the target [`Oscillator`]'s
+/// inputs to match.
+fn set_route(oscs: &[Mutex<Oscillator>], routing: &mut Routing, source: usize, target: usize, route: Option<Route>) {
+    if source == target {
+        return;
+    }
+    let mut lock = oscs[target].lock().unwrap();
+    if let Some(old) = routing.set_route(source, target, route) {
+        lock.remove_input(source, old.kind);
+    }
+    if let Some(route) = route {
+        lock.add_input(source, route.kind);
     }
 }
 
 /// Helper for handling [`Message`]s from other threads.
-fn parse_message(msg: Message, mixer: Arc<Mutex<Mixer>>, oscs: Arc<Vec<Mutex<Oscillator>>>) {
+fn parse_message(
+    msg: Message,
+    mixer: Arc<Mutex<Mixer>>,
+    oscs: Arc<Vec<Mutex<Oscillator>>>,
+    routing: Arc<Mutex<Routing>>,
+) {
     match msg {
+        Message::Additive{oscillator: i, wavetable} => {
+            // dropped here rather than while the Oscillator is locked:
+            let _previous = oscs[i].lock().unwrap().set_additive(wavetable);
+        }
+        Message::Algorithm(algorithm) => {
+            set_algorithm(&oscs, &mut routing.lock().unwrap(), algorithm);
+        }
+        Message::AmDepth{oscillator: i, depth} => {
+            oscs[i].lock().unwrap().set_am_depth(depth);
+        }
+        Message::AmpEnvelope(envelope) => {
+            mixer.lock().unwrap().amp.envelope = envelope;
+        }
+        Message::AmpSend{oscillator: i, send} => {
+            routing.lock().unwrap().set_master(i, send);
+        }
         Message::Attack(attack) => {
             mixer.lock().unwrap().amp.adsr.attack = attack;
         }
+        Message::AttackCurve(curve) => {
+            mixer.lock().unwrap().amp.adsr.attack_curve = curve;
+        }
         Message::Bypass{oscillator: i, bypass} => {
             oscs[i].lock().unwrap().bypass = bypass;
         }
@@ -189,42 +304,127 @@ fn parse_message(msg: Message, mixer: Arc<Mutex<Mixer>>, oscs: Arc<Vec<Mutex<Osc
         Message::Decay(decay) => {
             mixer.lock().unwrap().amp.adsr.decay = decay;
         }
+        Message::DecayCurve(curve) => {
+            mixer.lock().unwrap().amp.adsr.decay_curve = curve;
+        }
+        Message::Delay(delay) => {
+            mixer.lock().unwrap().amp.adsr.delay = delay;
+        }
+        Message::Drift{oscillator: i, drift} => {
+            oscs[i].lock().unwrap().set_drift(drift);
+        }
+        Message::EnvAmount{oscillator: i, amount} => {
+            oscs[i].lock().unwrap().set_env_amount(amount);
+        }
+        Message::EnvTarget{oscillator: i, target} => {
+            oscs[i].lock().unwrap().set_env_target(target);
+        }
+        Message::Feedback{oscillator: i, feedback} => {
+            oscs[i].lock().unwrap().set_feedback(feedback);
+        }
         Message::Fine{oscillator: i, fine} => {
             oscs[i].lock().unwrap().detune_fine(fine);
         }
+        Message::FmIndex{oscillator: i, index} => {
+            oscs[i].lock().unwrap().set_fm_index(index);
+        }
+        Message::FmMode{oscillator: i, mode} => {
+            oscs[i].lock().unwrap().set_fm_mode(mode);
+        }
         Message::FmRange{oscillator: i, range} => {
             oscs[i].lock().unwrap().set_fm_range(range);
         }
+        Message::FmRatio{oscillator: i, ratio} => {
+            oscs[i].lock().unwrap().set_fm_ratio(ratio);
+        }
         Message::Freq{oscillator: i, freq} => {
             oscs[i].lock().unwrap().set_freq(freq);
         }
         Message::Gain{oscillator: i, gain} => {
             oscs[i].lock().unwrap().set_gain(gain);
         }
+        Message::Hold(hold) => {
+            mixer.lock().unwrap().amp.adsr.hold = hold;
+        }
+        Message::KeyScaling(scaling) => {
+            mixer.lock().unwrap().amp.adsr.set_key_scaling(scaling);
+        }
+        Message::KeyTrack{oscillator: i, key_track} => {
+            oscs[i].lock().unwrap().set_key_track(key_track);
+        }
         Message::Master(gain) => {
             mixer.lock().unwrap().set_gain(gain);
         }
         Message::MixerMode(mode) => {
             mixer.lock().unwrap().set_mode(mode);
         }
-        Message::NoteOn{pitch, _velocity} => {
+        Message::MorphAmount{oscillator: i, amount} => {
+            oscs[i].lock().unwrap().set_morph_amount(amount);
+        }
+        Message::MorphAttack{oscillator: i, attack} => {
+            oscs[i].lock().unwrap().set_morph_attack(attack);
+        }
+        Message::MorphDecay{oscillator: i, decay} => {
+            oscs[i].lock().unwrap().set_morph_decay(decay);
+        }
+        Message::MorphPosition{oscillator: i, position} => {
+            oscs[i].lock().unwrap().set_morph_position(position);
+        }
+        Message::MorphSustain{oscillator: i, sustain} => {
+            oscs[i].lock().unwrap().set_morph_sustain(sustain);
+        }
+        Message::NoiseColor{oscillator: i, color} => {
+            oscs[i].lock().unwrap().set_noise_color(color);
+        }
+        Message::NoiseKeyTrack{oscillator: i, key_track} => {
+            oscs[i].lock().unwrap().set_noise_key_track(key_track);
+        }
+        Message::NoiseSeed{oscillator: i, seed} => {
+            oscs[i].lock().unwrap().set_noise_seed(seed);
+        }
+        Message::NoteOn{pitch, velocity} => {
             oscs.iter().for_each(|osc| {
                 let mut lock = osc.lock().unwrap();
                 if lock.get_mode() == PitchMode::MIDI {
-                    lock.note_on(pitch);
+                    lock.note_on(pitch, velocity);
+                } else {
+                    lock.track_note_on(pitch, velocity);
                 }
             });
-            mixer.lock().unwrap().amp.note_on(pitch);
+            mixer.lock().unwrap().amp.note_on(pitch, velocity);
         }
         Message::NoteOff{pitch} => {
             oscs.iter().for_each(|osc| {
                 let mut lock = osc.lock().unwrap();
                 if lock.get_mode() == PitchMode::MIDI {
                     lock.note_off(pitch);
+                } else {
+                    lock.track_note_off(pitch);
                 }
             });
             mixer.lock().unwrap().amp.note_off(pitch);
         }
+        Message::Octave{oscillator: i, octave} => {
+            oscs[i].lock().unwrap().detune_octave(octave);
+        }
+        Message::OscAmpAttack{oscillator: i, attack} => {
+            oscs[i].lock().unwrap().set_amp_attack(attack);
+        }
+        Message::OscAmpDecay{oscillator: i, decay} => {
+            oscs[i].lock().unwrap().set_amp_decay(decay);
+        }
+        Message::OscAmpEnabled{oscillator: i, enabled} => {
+            oscs[i].lock().unwrap().set_amp_enabled(enabled);
+        }
+        Message::OscAmpRelease{oscillator: i, release} => {
+            oscs[i].lock().unwrap().set_amp_release(release);
+        }
+        Message::OscAmpSustain{oscillator: i, sustain} => {
+            oscs[i].lock().unwrap().set_amp_sustain(sustain);
+        }
+        Message::PhaseMode{oscillator: i, mode} => {
+            oscs[i].lock().unwrap().set_phase_mode(mode);
+        }
         Message::PitchBend{lsb, msb} => {
             oscs.iter().for_each(|osc| {
                 let mut lock = osc.lock().unwrap();
@@ -234,33 +434,87 @@ fn parse_message(msg: Message, mixer: Arc<Mutex<Mixer>>, oscs: Arc<Vec<Mutex<Osc
         Message::PitchMode{oscillator: i, mode} => {
             oscs[i].lock().unwrap().set_mode(mode);
         }
-        Message::Output{oscillator: i, mode } => {
-            let mut lock = oscs[i].lock().unwrap();
-            match lock.get_output_mode() {
-                osc::oscillator::OutputMode::Master => (),
-                osc::oscillator::OutputMode::Osc(j) => {
-                    oscs[j].lock().unwrap().remove_fm_in(i);
-                }
-            }
-            match mode {
-                osc::oscillator::OutputMode::Master => {
-                    lock.set_output(mode);
-                }
-                osc::oscillator::OutputMode::Osc(j) => {
-                    lock.set_output(mode);
-                    oscs[j].lock().unwrap().add_fm_in(i);
-                }
-            }
+        Message::PluckBrightness{oscillator: i, brightness} => {
+            oscs[i].lock().unwrap().set_pluck_brightness(brightness);
+        }
+        Message::PluckDamping{oscillator: i, damping} => {
+            oscs[i].lock().unwrap().set_pluck_damping(damping);
+        }
+        Message::PluckPosition{oscillator: i, position} => {
+            oscs[i].lock().unwrap().set_pluck_position(position);
+        }
+        Message::PulseWidth{oscillator: i, width} => {
+            oscs[i].lock().unwrap().set_pulse_width(width);
+        }
+        Message::PwmRange{oscillator: i, range} => {
+            oscs[i].lock().unwrap().set_pwm_range(range);
         }
         Message::Release(release) => {
             mixer.lock().unwrap().amp.adsr.release = release;
         }
+        Message::ReleaseCurve(curve) => {
+            mixer.lock().unwrap().amp.adsr.release_curve = curve;
+        }
+        Message::Route{source: i, target: j, route} => {
+            set_route(&oscs, &mut routing.lock().unwrap(), i, j, route);
+        }
+        Message::Sample{oscillator: i, sample} => {
+            // the previous sample is dropped once the lock is released
+            let _previous = oscs[i].lock().unwrap().set_sample(sample);
+        }
+        Message::SampleLoopEnd{oscillator: i, end} => {
+            oscs[i].lock().unwrap().set_sample_loop_end(end);
+        }
+        Message::SampleLoopMode{oscillator: i, mode} => {
+            oscs[i].lock().unwrap().set_sample_loop_mode(mode);
+        }
+        Message::SampleLoopStart{oscillator: i, start} => {
+            oscs[i].lock().unwrap().set_sample_loop_start(start);
+        }
+        Message::SampleRoot{oscillator: i, root} => {
+            oscs[i].lock().unwrap().set_sample_root(root);
+        }
+        Message::SampleStart{oscillator: i, start} => {
+            oscs[i].lock().unwrap().set_sample_start(start);
+        }
+        Message::Stages{stages, sustain, looping} => {
+            if let Some(envelope) = &mut mixer.lock().unwrap().amp.stages {
+                envelope.set_stages(&stages, sustain, looping);
+            }
+        }
+        Message::StartPhase{oscillator: i, phase} => {
+            oscs[i].lock().unwrap().set_start_phase(phase);
+        }
         Message::Sustain(sustain) => {
             mixer.lock().unwrap().amp.adsr.set_sustain(sustain);
         }
+        Message::Tuning(tuning) => {
+            oscs.iter().for_each(|osc| osc.lock().unwrap().set_tuning(&tuning));
+        }
+        Message::TuningChange{notes} => {
+            oscs.iter().for_each(|osc| osc.lock().unwrap().retune(&notes));
+        }
+        Message::Unison{oscillator: i, voices} => {
+            oscs[i].lock().unwrap().set_unison_voices(voices);
+        }
+        Message::UnisonCurve{oscillator: i, curve} => {
+            oscs[i].lock().unwrap().set_unison_curve(curve);
+        }
+        Message::UnisonDetune{oscillator: i, detune} => {
+            oscs[i].lock().unwrap().set_unison_detune(detune);
+        }
+        Message::UnisonSpread{oscillator: i, spread} => {
+            oscs[i].lock().unwrap().set_unison_spread(spread);
+        }
+        Message::VelocityScaling(scaling) => {
+            mixer.lock().unwrap().amp.adsr.set_velocity_scaling(scaling);
+        }
         Message::Waveform{oscillator: i, waveform} => {
             oscs[i].lock().unwrap().set_waveform(waveform);
         }
+        Message::Wavetable{oscillator: i, wavetable} => {
+            oscs[i].lock().unwrap().set_wavetable(wavetable);
+        }
         _ => ()
     } 
 }
@@ -268,4 +522,92 @@ fn parse_message(msg: Message, mixer: Arc<Mutex<Mixer>>, oscs: Arc<Vec<Mutex<Osc
 /// Converts a gain value measured in decibels (`db`) to an amplitude value.
 pub fn db_to_amp(db: f64) -> f64 {
     f64::powf(10.0, db / 20.0)
-}
\ No newline at end of file
+}
+
+/// These tests check that a patch renders the same no matter which slots its [`Oscillator`]s occupy, which
+/// relies on [`Routing`] evaluating modulators before their targets and delaying only true cycles.
+#[cfg(test)]
+mod render_tests {
+    use super::*;
+    use osc::wave::Waveform;
+
+    /// Renders a patch with four roles: a carrier sent to the mixer, a modulator in a feedback cycle with a
+    /// second modulator, and a ring modulator. `slots[role]` decides where each role is placed.
+    fn render_patch(slots: [usize; NUM_OSCS]) -> Vec<(f64, f64)> {
+        let oscs: Vec<Mutex<Oscillator>> = (0..NUM_OSCS).map(|_| Mutex::new(Oscillator::new())).collect();
+        let mut routing = Routing::new();
+
+        let freqs = [220.0, 330.0, 110.0, 55.0];
+        let waveforms = [Waveform::Sine, Waveform::Sine, Waveform::Saw, Waveform::Triangle];
+        for role in 0..NUM_OSCS {
+            let mut osc = oscs[slots[role]].lock().unwrap();
+            osc.bypass = false;
+            osc.set_mode(PitchMode::Constant);
+            osc.set_freq(freqs[role]);
+            osc.set_waveform(waveforms[role].clone());
+            drop(osc);
+
+            routing.set_master(slots[role], role == 0);
+        }
+
+        let route = |kind, depth| Some(Route { kind, depth });
+        set_route(&oscs, &mut routing, slots[1], slots[0], route(ModKind::Fm, 0.8));
+        set_route(&oscs, &mut routing, slots[2], slots[1], route(ModKind::Fm, 0.5));
+        set_route(&oscs, &mut routing, slots[1], slots[2], route(ModKind::Fm, 0.3));
+        set_route(&oscs, &mut routing, slots[3], slots[0], route(ModKind::Ring, 0.6));
+
+        (0..2000).map(|_| render(&oscs, &mut routing, 0.0)).collect()
+    }
+
+    #[test]
+    fn patch_renders_identically_in_any_slots() {
+        let expected = render_patch([0, 1, 2, 3]);
+
+        for slots in [[3, 2, 1, 0], [1, 3, 0, 2], [2, 0, 3, 1]] {
+            let res = render_patch(slots);
+            for (a, b) in expected.iter().zip(res.iter()) {
+                assert!((a.0 - b.0).abs() < 1e-12 && (a.1 - b.1).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn seeded_noise_renders_bit_identically() {
+        let render_noise = |seed| {
+            let oscs: Vec<Mutex<Oscillator>> = (0..NUM_OSCS).map(|_| Mutex::new(Oscillator::new())).collect();
+            let mut routing = Routing::new();
+            for (i, osc) in oscs.iter().enumerate() {
+                let mut osc = osc.lock().unwrap();
+                osc.bypass = false;
+                osc.set_noise_seed(seed + i as u64);
+                osc.set_unison_voices(4);
+            }
+            oscs[0].lock().unwrap().set_waveform(Waveform::Noise);
+            oscs[1].lock().unwrap().set_noise_color(osc::noise::NoiseColor::Pink);
+            oscs[1].lock().unwrap().set_waveform(Waveform::Noise);
+
+            (0..2000).map(|_| render(&oscs, &mut routing, 0.0)).collect::<Vec<_>>()
+        };
+
+        assert_eq!(render_noise(42), render_noise(42));
+        assert_ne!(render_noise(42), render_noise(43));
+    }
+
+    #[test]
+    fn modulation_reaches_target_in_the_same_sample() {
+        // a modulator in a higher slot than its carrier must still affect the carrier's very first sample
+        let oscs: Vec<Mutex<Oscillator>> = (0..NUM_OSCS).map(|_| Mutex::new(Oscillator::new())).collect();
+        let mut routing = Routing::new();
+        for i in [0, 3] {
+            let mut osc = oscs[i].lock().unwrap();
+            osc.bypass = false;
+            osc.set_mode(PitchMode::Constant);
+        }
+        oscs[0].lock().unwrap().set_waveform(Waveform::Square);
+        routing.set_master(3, false);
+        set_route(&oscs, &mut routing, 3, 0, Some(Route { kind: ModKind::Ring, depth: 1.0 }));
+
+        // the square wave starts at -1.0, but the sine wave ring modulating it starts at 0.0:
+        assert_eq!(render(&oscs, &mut routing, 0.0), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,265 @@
+use super::amp::Curve;
+
+/// The most [`Stage`]s a [`MultiStage`] envelope can have.
+pub const MAX_STAGES: usize = 16;
+
+/// One segment of a [`MultiStage`] envelope, which moves from the level the previous `Stage` ended at to its own.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct Stage {
+    /// The shape of the move to `level`.
+    pub curve: Curve,
+    /// The level `[0..1]` the envelope reaches at the end of the `Stage`.
+    pub level: f64,
+    /// The time (in seconds) the `Stage` takes.
+    pub time: f64,
+}
+
+/// A breakpoint envelope of up to [`MAX_STAGES`] [`Stage`]s, for sounds that evolve in more steps than an
+/// [`Envelope`] allows. It can shape the [`Amplifier`]'s output in place of its [`Envelope`], and modulate any
+/// [`Oscillator`] (see [`EnvTarget`]).
+/// 
+/// The envelope starts from its current level each time a note begins, and plays its `Stage`s in order. While the
+/// note is held, it stops at the end of the `sustain` stage, or repeats the stages from `loop_start` to `loop_end`
+/// if a loop is set (the loop takes priority if it contains the sustain stage). Once the note ends, the envelope
+/// skips straight to the stage after `sustain`. Without a sustain stage, notes ending have no effect, so a loop
+/// repeats indefinitely. After the last stage, the envelope holds its final level.
+/// 
+/// Like the [`Amplifier`], the envelope is advanced by [`next()`] once per sample.
+/// 
+/// [`Amplifier`]:  super::amp::Amplifier
+/// [`EnvTarget`]:  super::osc::oscillator::EnvTarget
+/// [`Envelope`]:   super::amp::Envelope
+/// [`next()`]:     MultiStage::next()
+/// [`Oscillator`]: super::osc::oscillator::Oscillator
+pub struct MultiStage {
+    /// The time (in seconds) since the current stage began.
+    elapsed: f64,
+    /// The level the current stage started from.
+    from: f64,
+    /// Whether a note is currently held.
+    held: bool,
+    /// The level most recently returned by [`next()`](MultiStage::next()).
+    level: f64,
+    /// The first and last stage (inclusive) of the loop, if there is one.
+    looping: Option<(usize, usize)>,
+    /// The index of the stage being played, or `None` if the envelope has finished or hasn't started.
+    stage: Option<usize>,
+    /// The envelope's [`Stage`]s. Its capacity is always `MAX_STAGES`, so replacing them never reallocates.
+    stages: Vec<Stage>,
+    /// The stage that the envelope holds at the end of while a note is held, if there is one.
+    sustain: Option<usize>,
+}
+
+impl MultiStage {
+    /// Returns a new `MultiStage` envelope shaped like a simple ADSR envelope: a short attack, a decay to 0.7
+    /// that's sustained, and a release.
+    pub fn new() -> MultiStage {
+        let mut envelope = MultiStage {
+            elapsed: 0.0,
+            from: 0.0,
+            held: false,
+            level: 0.0,
+            looping: None,
+            stage: None,
+            stages: Vec::with_capacity(MAX_STAGES),
+            sustain: None,
+        };
+        envelope.set_stages(
+            &[
+                Stage { curve: Curve::Linear, level: 1.0, time: 0.01 },
+                Stage { curve: Curve::Exponential, level: 0.7, time: 0.3 },
+                Stage { curve: Curve::Exponential, level: 0.0, time: 0.5 },
+            ],
+            Some(1),
+            None,
+        );
+        envelope
+    }
+
+    /// Returns the level most recently returned by [`next()`](MultiStage::next()).
+    pub fn level(&self) -> f64 {
+        self.level
+    }
+
+    /// Returns the current level `[0..1]` of the envelope, then advances it by `step` seconds.
+    pub fn next(&mut self, step: f64) -> f64 {
+        // every stage could finish within one step if their times are short enough, but no more than that,
+        // so that a loop of instant stages can't run forever:
+        for _ in 0..=MAX_STAGES {
+            let Some(i) = self.stage else { break };
+            let stage = self.stages[i];
+
+            if self.elapsed < stage.time {
+                self.level = self.from + (stage.level - self.from) * stage.curve.apply(self.elapsed / stage.time);
+                break;
+            }
+            self.level = stage.level;
+            let in_loop = self.looping.is_some_and(|(start, end)| (start..=end).contains(&i));
+
+            match self.looping {
+                Some((start, end)) if i == end && (self.held || self.sustain.is_none()) => {
+                    self.enter(Some(start), self.elapsed - stage.time);
+                }
+                _ if self.held && self.sustain == Some(i) && !in_loop => break,
+                _ => self.enter(Some(i + 1).filter(|&next| next < self.stages.len()), self.elapsed - stage.time),
+            }
+        }
+        self.elapsed += step;
+
+        self.level
+    }
+
+    /// Signals the end of a note, moving the envelope to the stage after `self.sustain` if it hasn't passed it
+    /// already.
+    pub fn release(&mut self) {
+        self.held = false;
+
+        if let (Some(sustain), Some(stage)) = (self.sustain, self.stage) {
+            if stage <= sustain {
+                self.enter(Some(sustain + 1).filter(|&next| next < self.stages.len()), 0.0);
+            }
+        }
+    }
+
+    /// Replaces the envelope's [`Stage`]s, keeping only the first [`MAX_STAGES`], along with its `sustain` stage
+    /// and `looping` stages. Each `Stage`'s level is restricted to the range `[0..1]`, and its time to be
+    /// non-negative. Sustain and loop stages that don't exist are ignored, as are loops that end before they
+    /// start.
+    /// 
+    /// If the stage being played no longer exists, the envelope finishes.
+    pub fn set_stages(&mut self, stages: &[Stage], sustain: Option<usize>, looping: Option<(usize, usize)>) {
+        self.stages.clear();
+        self.stages.extend(stages.iter().take(MAX_STAGES).map(|stage| Stage {
+            curve: stage.curve,
+            level: stage.level.clamp(0.0, 1.0),
+            time: stage.time.max(0.0),
+        }));
+
+        self.sustain = sustain.filter(|&sustain| sustain < self.stages.len());
+        self.looping = looping.filter(|&(start, end)| start <= end && end < self.stages.len());
+        if self.stage.is_some_and(|stage| stage >= self.stages.len()) {
+            self.stage = None;
+        }
+    }
+
+    /// Signals the start of a note, restarting the envelope from its first stage at its current level.
+    pub fn trigger(&mut self) {
+        self.held = true;
+        self.enter(Some(0).filter(|_| !self.stages.is_empty()), 0.0);
+    }
+
+    /// Starts playing `stage` from the current level, `elapsed` seconds in.
+    fn enter(&mut self, stage: Option<usize>, elapsed: f64) {
+        self.stage = stage;
+        self.from = self.level;
+        self.elapsed = elapsed;
+    }
+}
+
+#[cfg(test)]
+mod multistage_tests {
+    use super::*;
+
+    fn stage(level: f64, time: f64) -> Stage {
+        Stage { curve: Curve::Linear, level, time }
+    }
+
+    /// Renders `length` samples of `envelope` at 1kHz.
+    fn render(envelope: &mut MultiStage, length: usize) -> Vec<f64> {
+        (0..length).map(|_| envelope.next(0.001)).collect()
+    }
+
+    #[test]
+    fn stages_play_in_order_and_sustain() {
+        let mut envelope = MultiStage::new();
+        envelope.set_stages(&[stage(1.0, 0.01), stage(0.5, 0.01), stage(0.25, 0.01), stage(0.0, 0.01)], Some(2), None);
+
+        envelope.trigger();
+        let levels = render(&mut envelope, 50);
+        assert!((levels[5] - 0.5).abs() < 1e-9);
+        assert!((levels[15] - 0.75).abs() < 1e-9);
+        assert!(levels[31..].iter().all(|&level| level == 0.25));
+
+        envelope.release();
+        let levels = render(&mut envelope, 20);
+        assert!((levels[5] - 0.125).abs() < 1e-9);
+        assert_eq!(levels[15], 0.0);
+    }
+
+    #[test]
+    fn loops_while_held() {
+        let mut envelope = MultiStage::new();
+        envelope.set_stages(
+            &[stage(1.0, 0.01), stage(0.0, 0.01), stage(1.0, 0.01), stage(0.0, 0.01)],
+            Some(2),
+            Some((1, 2)),
+        );
+
+        envelope.trigger();
+        let levels = render(&mut envelope, 60);
+        // after the first stage, the envelope bounces between 0 and 1 every 10ms:
+        for (i, &level) in levels.iter().enumerate().skip(10).step_by(10) {
+            assert!((level - if i % 20 == 10 { 1.0 } else { 0.0 }).abs() < 1e-6);
+        }
+
+        // releasing part-way through the loop moves to the final stage from the current level:
+        render(&mut envelope, 6);
+        envelope.release();
+        let levels = render(&mut envelope, 20);
+        assert!((levels[0] - 0.5).abs() < 1e-9);
+        assert!((levels[5] - 0.25).abs() < 1e-9);
+        assert_eq!(levels[15], 0.0);
+    }
+
+    #[test]
+    fn loop_containing_sustain_keeps_looping() {
+        let mut envelope = MultiStage::new();
+        envelope.set_stages(
+            &[stage(1.0, 0.01), stage(0.0, 0.01), stage(1.0, 0.01), stage(0.0, 0.01)],
+            Some(1),
+            Some((1, 2)),
+        );
+
+        envelope.trigger();
+        let levels = render(&mut envelope, 80);
+        // the sustain stage starts the loop, so the envelope keeps bouncing instead of holding at 0:
+        for (i, &level) in levels.iter().enumerate().skip(20).step_by(10) {
+            assert!((level - if i % 20 == 10 { 1.0 } else { 0.0 }).abs() < 1e-6);
+        }
+
+        // once released, the loop finishes its current pass and the envelope moves on to the last stage:
+        envelope.release();
+        let levels = render(&mut envelope, 50);
+        assert!((levels[10] - 1.0).abs() < 1e-6);
+        assert!(levels.iter().skip(20).all(|&level| level == 0.0));
+    }
+
+    #[test]
+    fn retrigger_starts_from_current_level() {
+        let mut envelope = MultiStage::new();
+        envelope.set_stages(&[stage(1.0, 0.01), stage(0.0, 0.01)], None, None);
+
+        envelope.trigger();
+        render(&mut envelope, 16);
+        envelope.trigger();
+        let levels = render(&mut envelope, 10);
+        assert!((levels[0] - 0.5).abs() < 1e-9);
+        assert!((levels[5] - 0.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn instant_loops_terminate() {
+        let mut envelope = MultiStage::new();
+        envelope.set_stages(&[stage(1.0, 0.0), stage(0.0, 0.0)], None, Some((0, 1)));
+
+        envelope.trigger();
+        let level = envelope.next(0.001);
+        assert!(level == 0.0 || level == 1.0);
+
+        // stages beyond MAX_STAGES, and loops that don't fit, are dropped:
+        envelope.set_stages(&[stage(0.5, 0.1); MAX_STAGES + 4], Some(MAX_STAGES), Some((2, 1)));
+        assert_eq!(envelope.stages.len(), MAX_STAGES);
+        assert_eq!(envelope.sustain, None);
+        assert_eq!(envelope.looping, None);
+    }
+}
@@ -0,0 +1,80 @@
+/// Calculates the polynomial band-limited step (PolyBLEP) correction for a discontinuity at phase `0.0`.
+/// 
+/// `t` is the current position `[0..1)` within the waveform's cycle, and `dt` is how far the phase moves
+/// each sample (frequency divided by sample rate). The returned value should be added to a naive waveform for
+/// an upward step of size 2, or subtracted for a downward step. Samples further than `dt` from the step are
+/// unaffected.
+/// 
+/// Rather than trying to band-limit a sharp edge ahead of time like the static tables in [`osc`], this
+/// smooths the two samples surrounding each edge as they are generated, which removes most of the aliasing
+/// that a jump in amplitude would otherwise produce.
+/// 
+/// [`osc`]:    super
+pub fn poly_blep(t: f64, dt: f64) -> f64 {
+    if dt <= 0.0 {
+        0.0
+    } else if t < dt {
+        // just after the step
+        let t = t / dt;
+        t + t - t * t - 1.0
+    } else if t > 1.0 - dt {
+        // just before the step
+        let t = (t - 1.0) / dt;
+        t * t + t + t + 1.0
+    } else {
+        0.0
+    }
+}
+
+/// Generates a band-limited pulse wave sample at position `t` `[0..1)` of the cycle, where `width` `(0..1)`
+/// is the fraction of the cycle spent at the high amplitude.
+/// 
+/// The naive pulse is centered around zero, so that changing the width doesn't shift the signal's DC offset,
+/// and its edges are corrected with [`poly_blep()`]. Centering moves the longer half of the cycle towards zero and
+/// the shorter half away from it, so the result is scaled back down to fit `[-1.0..1.0]` like every other
+/// [`Waveform`](super::wave::Waveform).
+pub fn pulse(t: f64, dt: f64, width: f64) -> f64 {
+    let naive = if t < width { 1.0 } else { -1.0 };
+    let falling = (t - width).rem_euclid(1.0);
+    let offset = 2.0 * width - 1.0;
+
+    (naive + poly_blep(t, dt) - poly_blep(falling, dt) - offset) / (1.0 + offset.abs())
+}
+
+#[cfg(test)]
+mod blep_tests {
+    use super::*;
+
+    #[test]
+    fn poly_blep_only_affects_edges() {
+        let dt = 0.01;
+
+        assert_eq!(poly_blep(0.5, dt), 0.0);
+        assert_eq!(poly_blep(dt, dt), 0.0);
+        assert_eq!(poly_blep(1.0 - dt, dt), 0.0);
+    }
+
+    #[test]
+    fn pulse_has_no_dc_offset() {
+        let dt = 1.0 / 480.0;
+
+        for width in [0.05, 0.25, 0.5, 0.95] {
+            let mut sum = 0.0;
+            for i in 0..480 {
+                sum += pulse(i as f64 * dt, dt, width);
+            }
+            assert!((sum / 480.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn pulse_stays_in_range() {
+        let dt = 1.0 / 480.0;
+
+        for width in [0.05, 0.25, 0.5, 0.95] {
+            let peak = (0..480).map(|i| pulse(i as f64 * dt, dt, width).abs()).fold(0.0, f64::max);
+            assert!(peak <= 1.0 + 1e-9);
+            assert!(peak > 0.99);
+        }
+    }
+}
@@ -2,8 +2,15 @@ use std::{array, sync::LazyLock};
 
 use wave::Waveform;
 
+pub mod blep;
+pub mod morph;
+pub mod noise;
 pub mod oscillator;
+pub mod pluck;
+pub mod sample;
+pub mod unison;
 pub mod wave;
+pub mod wavetable;
 
 /// Number of samples stored in the reference tables for each waveform.
 const TABLE_LENGTH: usize = 1024;
@@ -0,0 +1,123 @@
+/// Controls the position an [`Oscillator`] reads from within a multi-frame [`Wavetable`], which can sweep
+/// between frames with its own envelope to change the timbre over the course of a note.
+/// 
+/// The envelope restarts at each MIDI note-on, and is shaped like the attack, decay, and sustain of an amplitude
+/// envelope: it rises from 0 to 1 over `attack`, then falls to `sustain` over `decay`. Its level is scaled by
+/// `amount` and added to the base `position`.
+/// 
+/// Like the [`Amplifier`]'s envelope, it's advanced by [`next()`] once per sample, so it stays in step with the
+/// `Oscillator`'s output.
+/// 
+/// [`Amplifier`]:      crate::synth::amp::Amplifier
+/// [`next()`]:         Morph::next()
+/// [`Oscillator`]:     super::oscillator::Oscillator
+/// [`Wavetable`]:      super::wavetable::Wavetable
+pub struct Morph {
+    /// How far `[-1..1]` the envelope moves the position at its peak.
+    amount: f64,
+    /// The time (in seconds) the envelope takes to reach its peak after a note-on.
+    attack: f64,
+    /// The time (in seconds) the envelope takes to fall to `sustain` after the attack.
+    decay: f64,
+    /// The time (in seconds) since the envelope last restarted.
+    elapsed: f64,
+    /// The base position `[0..1]` within the `Wavetable`, from its first frame to its last.
+    position: f64,
+    /// The level `[0..1]` the envelope holds at after the attack and decay.
+    sustain: f64,
+}
+
+impl Morph {
+    /// Returns a new `Morph` at the first frame, with no envelope.
+    pub fn new() -> Morph {
+        Morph {
+            amount: 0.0,
+            attack: 0.0,
+            decay: 0.0,
+            elapsed: 0.0,
+            position: 0.0,
+            sustain: 1.0,
+        }
+    }
+
+    /// Returns the current position `[0..1]` within the `Wavetable`, then advances the envelope by `step`
+    /// seconds.
+    pub fn next(&mut self, step: f64) -> f64 {
+        let level = if self.elapsed < self.attack {
+            self.elapsed / self.attack
+        } else if self.elapsed < self.attack + self.decay {
+            1.0 - (1.0 - self.sustain) * (self.elapsed - self.attack) / self.decay
+        } else {
+            self.sustain
+        };
+        self.elapsed += step;
+
+        (self.position + self.amount * level).clamp(0.0, 1.0)
+    }
+
+    /// Restarts the envelope from the beginning of its attack.
+    pub fn restart(&mut self) {
+        self.elapsed = 0.0;
+    }
+
+    /// Restricts `amount` to the range `[-1..1]`, then replaces `self.amount`.
+    pub fn set_amount(&mut self, amount: f64) {
+        self.amount = amount.clamp(-1.0, 1.0);
+    }
+
+    /// Restricts `attack` to be non-negative, then replaces `self.attack`.
+    pub fn set_attack(&mut self, attack: f64) {
+        self.attack = attack.max(0.0);
+    }
+
+    /// Restricts `decay` to be non-negative, then replaces `self.decay`.
+    pub fn set_decay(&mut self, decay: f64) {
+        self.decay = decay.max(0.0);
+    }
+
+    /// Restricts `position` to the range `[0..1]`, then replaces `self.position`.
+    pub fn set_position(&mut self, position: f64) {
+        self.position = position.clamp(0.0, 1.0);
+    }
+
+    /// Restricts `sustain` to the range `[0..1]`, then replaces `self.sustain`.
+    pub fn set_sustain(&mut self, sustain: f64) {
+        self.sustain = sustain.clamp(0.0, 1.0);
+    }
+}
+
+#[cfg(test)]
+mod morph_tests {
+    use super::*;
+
+    #[test]
+    fn envelope_sweeps_from_position() {
+        let mut morph = Morph::new();
+        morph.set_position(0.25);
+        morph.set_amount(0.5);
+        morph.set_attack(1.0);
+        morph.set_decay(1.0);
+        morph.set_sustain(0.5);
+
+        assert_eq!(morph.next(0.5), 0.25);
+        assert_eq!(morph.next(0.5), 0.5);
+        assert_eq!(morph.next(0.5), 0.75);
+        assert_eq!(morph.next(1.0), 0.625);
+        assert_eq!(morph.next(1.0), 0.5);
+
+        morph.restart();
+        assert_eq!(morph.next(0.0), 0.25);
+    }
+
+    #[test]
+    fn position_is_clamped() {
+        let mut morph = Morph::new();
+        morph.set_position(0.75);
+        morph.set_amount(1.0);
+
+        assert_eq!(morph.next(1.0), 1.0);
+
+        morph.set_amount(-1.0);
+        assert_eq!(morph.next(1.0), 0.0);
+    }
+}
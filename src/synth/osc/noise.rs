@@ -0,0 +1,193 @@
+use crate::PI;
+
+/// The seed used by every [`Prng`] until it's given another, so that renders are reproducible by default.
+pub const DEFAULT_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// A small, fast pseudorandom number generator (xorshift64*), owned by each [`Oscillator`] so that its noise
+/// and random phases are reproducible from a seed and don't need a thread-local generator every sample.
+/// 
+/// [`Oscillator`]: super::oscillator::Oscillator
+pub struct Prng {
+    state: u64,
+}
+
+impl Prng {
+    /// Returns a new `Prng` starting from `seed`.
+    pub fn new(seed: u64) -> Prng {
+        // xorshift can't leave a state of 0, so that seed is replaced
+        Prng { state: if seed == 0 { DEFAULT_SEED } else { seed } }
+    }
+
+    /// Returns the next random value in the range `[0..1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        let value = self.state.wrapping_mul(0x2545_f491_4f6c_dd1d);
+
+        // the top 53 bits fill an f64's mantissa exactly:
+        (value >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// The spectral "color" of the noise generated by an [`Oscillator`] set to [`Waveform::Noise`].
+/// 
+/// [`Oscillator`]:         super::oscillator::Oscillator
+/// [`Waveform::Noise`]:    super::wave::Waveform::Noise
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum NoiseColor {
+    /// Equal energy at every frequency.
+    White,
+    /// Energy falls by 3dB per octave, so each octave has equal energy. Sounds like rainfall.
+    Pink,
+    /// Energy falls by 6dB per octave. Sounds like a deep rumble or surf.
+    Brown,
+    /// Energy rises by 3dB per octave. Sounds like a hiss.
+    Blue,
+}
+
+/// Generates noise of a [`NoiseColor`] from white noise, optionally band-pass filtered around the `Oscillator`'s
+/// frequency ("key tracking") so that the noise has a pitch.
+pub struct Noise {
+    /// The state of the leaky integrator used to generate brown noise.
+    brown: f64,
+    /// The [`NoiseColor`] generated.
+    color: NoiseColor,
+    /// The state of the key tracking filter.
+    filter: (f64, f64),
+    /// Whether the noise is band-pass filtered around the `Oscillator`'s frequency.
+    key_track: bool,
+    /// The previous pink noise sample, used to generate blue noise.
+    last_pink: f64,
+    /// The state of the filters used to generate pink noise (Paul Kellet's refined method).
+    pink: [f64; 7],
+}
+
+impl Noise {
+    /// The resonance of the key tracking filter. Higher values give the noise a clearer pitch.
+    const KEY_TRACK_Q: f64 = 8.0;
+
+    /// Returns a new `Noise` generating unfiltered white noise.
+    pub fn new() -> Noise {
+        Noise {
+            brown: 0.0,
+            color: NoiseColor::White,
+            filter: (0.0, 0.0),
+            key_track: false,
+            last_pink: 0.0,
+            pink: [0.0; 7],
+        }
+    }
+
+    /// Returns the next noise sample `[-1..1]`, using `rng` for the underlying white noise.
+    /// 
+    /// `frequency` and `sample_rate` are only used when `self.key_track` is true, to tune the band-pass filter (a
+    /// state-variable filter, normalized to unity gain at its center).
+    pub fn next(&mut self, rng: &mut Prng, frequency: f64, sample_rate: f64) -> f64 {
+        let white = rng.next_f64() * 2.0 - 1.0;
+
+        let sample = match self.color {
+            NoiseColor::White => white,
+            NoiseColor::Pink => self.pink(white),
+            NoiseColor::Brown => {
+                self.brown = (self.brown + 0.02 * white) / 1.02;
+                (self.brown * 3.5).clamp(-1.0, 1.0)
+            }
+            NoiseColor::Blue => {
+                let pink = self.pink(white);
+                let blue = pink - self.last_pink;
+                self.last_pink = pink;
+                blue.clamp(-1.0, 1.0)
+            }
+        };
+
+        if !self.key_track || sample_rate <= 0.0 {
+            return sample;
+        }
+
+        let cutoff = frequency.abs().clamp(20.0, 0.49 * sample_rate);
+        let g = (PI * cutoff / sample_rate).tan();
+        let k = 1.0 / Noise::KEY_TRACK_Q;
+        let a1 = 1.0 / (1.0 + g * (g + k));
+        let a2 = g * a1;
+        let a3 = g * a2;
+
+        let (ic1, ic2) = self.filter;
+        let v3 = sample - ic2;
+        let v1 = a1 * ic1 + a2 * v3;
+        let v2 = ic2 + a2 * ic1 + a3 * v3;
+        self.filter = (2.0 * v1 - ic1, 2.0 * v2 - ic2);
+
+        (v1 * k).clamp(-1.0, 1.0)
+    }
+
+    /// Replaces `self.color` with `color`.
+    pub fn set_color(&mut self, color: NoiseColor) {
+        self.color = color;
+    }
+
+    /// Replaces `self.key_track` with `key_track`.
+    pub fn set_key_track(&mut self, key_track: bool) {
+        self.key_track = key_track;
+        self.filter = (0.0, 0.0);
+    }
+
+    /// Filters `white` noise to pink noise.
+    fn pink(&mut self, white: f64) -> f64 {
+        let b = &mut self.pink;
+        b[0] = 0.99886 * b[0] + white * 0.0555179;
+        b[1] = 0.99332 * b[1] + white * 0.0750759;
+        b[2] = 0.96900 * b[2] + white * 0.1538520;
+        b[3] = 0.86650 * b[3] + white * 0.3104856;
+        b[4] = 0.55000 * b[4] + white * 0.5329522;
+        b[5] = -0.7616 * b[5] - white * 0.0168980;
+        let pink = b.iter().sum::<f64>() + white * 0.5362;
+        b[6] = white * 0.115926;
+
+        (pink * 0.11).clamp(-1.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod noise_tests {
+    use super::*;
+
+    /// The average squared difference between consecutive samples, relative to the average squared sample. This
+    /// is higher for noise with more high-frequency energy.
+    fn roughness(color: NoiseColor) -> f64 {
+        let mut rng = Prng::new(DEFAULT_SEED);
+        let mut noise = Noise::new();
+        noise.set_color(color);
+
+        let samples: Vec<f64> = (0..48000).map(|_| noise.next(&mut rng, 0.0, 48000.0)).collect();
+        let power: f64 = samples.iter().map(|s| s * s).sum();
+        let difference: f64 = samples.windows(2).map(|w| (w[1] - w[0]).powi(2)).sum();
+        difference / power
+    }
+
+    #[test]
+    fn same_seed_gives_same_noise() {
+        let mut a = Prng::new(1234);
+        let mut b = Prng::new(1234);
+        let mut c = Prng::new(4321);
+
+        let a: Vec<f64> = (0..100).map(|_| a.next_f64()).collect();
+        let b: Vec<f64> = (0..100).map(|_| b.next_f64()).collect();
+        let c: Vec<f64> = (0..100).map(|_| c.next_f64()).collect();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert!(a.iter().all(|&x| (0.0..1.0).contains(&x)));
+    }
+
+    #[test]
+    fn colors_are_ordered_by_brightness() {
+        let brown = roughness(NoiseColor::Brown);
+        let pink = roughness(NoiseColor::Pink);
+        let white = roughness(NoiseColor::White);
+        let blue = roughness(NoiseColor::Blue);
+
+        assert!(brown < pink);
+        assert!(pink < white);
+        assert!(white < blue);
+    }
+}
@@ -1,8 +1,30 @@
-use std::collections::HashMap;
+use std::{collections::{HashMap, HashSet}, sync::Arc};
 
-use crate::synth::{self, MIDI_TO_HZ};
+use crate::{synth::{self, amp::Amplifier, routing::ModKind, tuning::Tuning}, PI};
 
-use super::{wave::Waveform, *};
+use super::{
+    blep,
+    morph::Morph,
+    noise::{self, Noise, NoiseColor, Prng},
+    pluck::Pluck,
+    sample::{LoopMode, Sample, Sampler},
+    unison::{self, DetuneCurve, Unison},
+    wave::Waveform,
+    wavetable::Wavetable,
+    *,
+};
+
+/// The time (in seconds) between each new random target that a [`PitchController`]'s drift wanders towards.
+const DRIFT_INTERVAL: f64 = 0.5;
+
+/// How far (in semitones) the [`MultiStage`] envelope moves an `Oscillator`'s pitch at full level and amount, in
+/// [`EnvTarget::Pitch`].
+/// 
+/// [`MultiStage`]: synth::multistage::MultiStage
+const ENV_PITCH_RANGE: f64 = 24.0;
+
+/// The time (in seconds) over which [`Waveform::Additive`] crossfades to a new table when the harmonics change.
+const ADDITIVE_FADE: f64 = 0.01;
 
 /// The basic sound-generation object for the application.
 /// 
@@ -14,30 +36,114 @@ use super::{wave::Waveform, *};
 /// 
 /// [`osc::init_tables()`]: init_tables()
 pub struct Oscillator {
+    /// The [`Wavetable`] played by [`Waveform::Additive`], built from the harmonics set in the UI.
+    additive: Arc<Wavetable>,
+    /// How much `[0..1]` of `self.additive_previous` is still mixed into the output, which fades out over
+    /// [`ADDITIVE_FADE`] seconds so that editing the harmonics doesn't cause clicks.
+    additive_fade: f64,
+    /// The [`Wavetable`] that `self.additive` replaced. It's kept (rather than dropped as soon as it's faded out)
+    /// until the next replacement, so that it's never deallocated on the audio thread.
+    additive_previous: Arc<Wavetable>,
+    /// Determines how strongly `[0..1]` AM and ring modulation inputs affect the `Oscillator`'s amplitude.
+    am_depth: f64,
+    /// Allows the amplitude of the `Oscillator` to be scaled by the signal from other `Oscillator`s via
+    /// amplitude modulation.
+    am_in: HashMap<usize, f64>,
+    /// The `Oscillator`'s own amplitude envelope, triggered by every MIDI note. While `amp_enabled` is `true`,
+    /// it shapes the output before it's routed anywhere, so a modulator's depth can change over a note's life.
+    amp: Amplifier,
+    /// Whether `amp` shapes the output.
+    amp_enabled: bool,
     /// Determines whether the `Oscillator` is "bypassed" - if true, it produces no signal.
     pub bypass: bool,
+    /// How strongly `[-1..1]` the [`MultiStage`] envelope modulates `self.env_target`. Negative amounts move it
+    /// the opposite way.
+    /// 
+    /// [`MultiStage`]: synth::multistage::MultiStage
+    env_amount: f64,
+    /// The latest level `[0..1]` of the [`MultiStage`] envelope, for `self`'s next calculation.
+    /// 
+    /// [`MultiStage`]: synth::multistage::MultiStage
+    env_level: f64,
+    /// The [`EnvTarget`] that the [`MultiStage`] envelope modulates.
+    /// 
+    /// [`MultiStage`]: synth::multistage::MultiStage
+    env_target: EnvTarget,
+    /// How strongly `[0..1]` the `Oscillator`'s own output modulates its phase.
+    feedback: f64,
+    /// The last two samples generated by each unison voice, used for self-feedback. See [`calc()`].
+    /// 
+    /// [`calc()`]: Oscillator::calc()
+    feedback_history: [(f64, f64); unison::MAX_VOICES],
     /// Allows the frequency of the `Oscillator` to be modified by the amplitude
     /// of the signal from other `Oscillator`s via frequency modulation.
     fm_in: HashMap<usize, f64>,
-    /// Determines the range by which an FM input can modulate the `Oscillator`'s frequency.
+    /// The modulation index `[0..10]` applied to FM inputs in [`FmMode::Ratio`], measured in radians of phase
+    /// deviation per unit of modulator amplitude.
+    fm_index: f64,
+    /// The [`FmMode`] describing how FM inputs modify the `Oscillator`.
+    fm_mode: FmMode,
+    /// Determines the range by which an FM input can modulate the `Oscillator`'s frequency in [`FmMode::Linear`].
     fm_range: u16,
+    /// The multiplier `[0.5..16]` applied to the `Oscillator`'s frequency in [`FmMode::Ratio`].
+    fm_ratio: f64,
     /// The overall volume modifier of the signal. Stored in the struct, this field is measured as an
     /// amplitude multiplier, e.g. some value typically in the range `[0..1]`. However, when modified by
     /// user input, the public-facing value is measured in dB as that is more commonly used by audio
     /// professionals and musicians.
     gain: f64,
-    /// The [`OutputMode`] describing the output target for this `Oscillator`'s signal. 
-    output_mode: OutputMode,
+    /// The [`Morph`] controlling where `self.wavetable` is read from between its frames.
+    morph: Morph,
+    /// The [`Noise`] generator used by [`Waveform::Noise`].
+    noise: Noise,
     /// Modified and rounded to increment the index the `Oscillator` uses to access values from its [`Waveform`]'s
-    /// corresponding table stored in [`osc`].
+    /// corresponding table stored in [`osc`]. Each unison voice keeps its own phase.
     /// 
     /// [`osc`]:    super
-    phase: f64,
+    phases: [f64; unison::MAX_VOICES],
+    /// The [`PhaseMode`] deciding what happens to the phase of each voice when a note starts.
+    phase_mode: PhaseMode,
+    /// The [`Pluck`] string model used by [`Waveform::Pluck`].
+    pluck: Pluck,
     /// A [`PitchController`] that handles calculation and modification of the `Oscillator`'s frequency
     /// (aside from FM).
     pitch_controller: PitchController,
+    /// The seeded random number generator behind `self.noise` and any random phases, so that the `Oscillator`'s
+    /// output is reproducible.
+    rng: Prng,
+    /// The [`Sampler`] used by [`Waveform::Sample`].
+    sampler: Sampler,
+    /// The phase (in degrees) `[0..360]` that notes start at in [`PhaseMode::Reset`].
+    start_phase: f64,
+    /// The fraction `[0.05..0.95]` of each cycle that a [`Waveform::Pulse`] spends at its high amplitude.
+    pulse_width: f64,
+    /// Allows the pulse width of the `Oscillator` to be modified by the amplitude of the signal from other
+    /// `Oscillator`s.
+    pwm_in: HashMap<usize, f64>,
+    /// Determines the range `[0..0.45]` by which a PWM input can modulate the `Oscillator`'s pulse width.
+    pwm_range: f64,
+    /// Allows the signal of the `Oscillator` to be multiplied by the signal from other `Oscillator`s via ring
+    /// modulation.
+    ring_in: HashMap<usize, f64>,
+    /// The indexes of the `Oscillator`s that hard sync (reset the phase of) this `Oscillator`.
+    sync_in: HashSet<usize>,
+    /// The previous output sample, held back by one sample while any sync input is connected. See [`calc()`].
+    /// 
+    /// [`calc()`]: Oscillator::calc()
+    sync_delay: (f64, f64),
+    /// If a sync input completed a cycle during its last sample, how far (as a fraction of a sample) past the
+    /// end of the cycle it was.
+    sync_pending: Option<f64>,
+    /// If this `Oscillator` completed a cycle during its last sample, how far (as a fraction of a sample) past
+    /// the end of the cycle it was. This is sent to any `Oscillator` this one syncs.
+    sync_out: Option<f64>,
+    /// The [`Unison`] settings describing how many detuned voices this `Oscillator` stacks.
+    unison: Unison,
     /// The sound wave shape generated by this `Oscillator`.
     waveform: Waveform,
+    /// The [`Wavetable`] played by [`Waveform::Custom`], if one has been loaded. It's kept when switching to
+    /// another `Waveform`, so switching back doesn't require loading it again.
+    wavetable: Option<Arc<Wavetable>>,
 }
 
 impl Oscillator {
@@ -45,56 +151,234 @@ impl Oscillator {
     /// 
     /// Currently, none of the values are taken as arguments.
     pub fn new() -> Oscillator {
+        let additive = Arc::new(Wavetable::from_harmonics(&[1.0], &[]));
+
         Oscillator {
+            additive: Arc::clone(&additive),
+            additive_fade: 0.0,
+            additive_previous: additive,
+            am_depth: 1.0,
+            am_in: HashMap::new(),
+            amp: Amplifier::default(),
+            amp_enabled: false,
             bypass: true,
+            env_amount: 0.0,
+            env_level: 0.0,
+            env_target: EnvTarget::Pitch,
+            feedback: 0.0,
+            feedback_history: [(0.0, 0.0); unison::MAX_VOICES],
             fm_in: HashMap::new(),
+            fm_index: 1.0,
+            fm_mode: FmMode::Linear,
             fm_range: 100,
+            fm_ratio: 1.0,
             gain: 1.0,
-            output_mode: OutputMode::Master,
-            phase: 0.0,
+            morph: Morph::new(),
+            noise: Noise::new(),
+            phases: [0.0; unison::MAX_VOICES],
+            phase_mode: PhaseMode::Reset,
             pitch_controller: PitchController::new(),
+            pluck: Pluck::new(),
+            pulse_width: 0.5,
+            pwm_in: HashMap::new(),
+            pwm_range: 0.25,
+            ring_in: HashMap::new(),
+            rng: Prng::new(noise::DEFAULT_SEED),
+            sampler: Sampler::new(),
+            start_phase: 0.0,
+            sync_in: HashSet::new(),
+            sync_delay: (0.0, 0.0),
+            sync_pending: None,
+            sync_out: None,
+            unison: Unison::new(),
             waveform: Waveform::Sine,
+            wavetable: None,
         }
     }
 
-    /// Calculates the current output sample for `self` in the form of an amplitude value `[-1.0..1.0]`.
+    /// Calculates the current output sample for `self` in the form of left and right amplitude values
+    /// `[-1.0..1.0]`.
+    /// 
+    /// If `self.bypass` is set to true, these values will be `0.0`. Otherwise, the `Oscillator` will apply any FM
+    /// and PWM inputs, then for each unison voice look up the appropriate sample value for its [`Waveform`], pan it,
+    /// and increment its `phase`. [`Waveform::Pulse`] has no table, and is instead generated with
+    /// [`blep::pulse()`]. [`Waveform::Custom`] reads from `self.wavetable`, and is silent until one is loaded. Its
+    /// position between the table's frames comes from `self.morph`, which is advanced once per sample.
+    /// [`Waveform::Additive`] reads from `self.additive`, crossfading from the table it replaced if it was just
+    /// changed. [`Waveform::Noise`], [`Waveform::Pluck`], and [`Waveform::Sample`] are generated once per sample by
+    /// `self.noise`, `self.pluck`, and `self.sampler`, ignoring the phase and unison: they play as a single centered
+    /// voice, at the same level whatever the number of unison voices.
     /// 
-    /// If `self.bypass` is set to true, this value will be `0.0`. Otherwise, the `Oscillator` will apply any FM inputs,
-    /// look up the appropriate sample value for its [`Waveform`], and increment its `phase`.
+    /// In [`FmMode::Ratio`], the frequency is multiplied by `self.fm_ratio` and FM inputs offset the phase used
+    /// to look up each sample instead of changing the frequency, so the timbre stays consistent across the
+    /// keyboard. Self-feedback offsets the phase the same way, using the voice's own previous output.
     /// 
-    /// [`Constant`]:   PitchMode::Constant
-    /// [`MIDI`]:       PitchMode::MIDI
-    pub fn calc(&mut self) -> f64 {
+    /// If `self.amp_enabled` is true, the output is shaped by the `Oscillator`'s own envelope in `self.amp`,
+    /// which is only advanced while it's enabled.
+    /// 
+    /// AM and ring modulation inputs are applied to the sum of the voices. AM inputs are treated as unipolar, so
+    /// at full depth they move the amplitude between silence and its normal level, while ring modulation inputs
+    /// multiply the signal directly. In both cases, `self.am_depth` blends between the modulated and dry signal.
+    /// Each input has already been scaled by its [`Route`]'s depth, so the two depths multiply (see
+    /// [`Route::apply()`]).
+    /// 
+    /// If a hard sync input reset the `Oscillator` since the last sample, every voice restarts its cycle. The jump
+    /// this causes is smoothed with [`blep::poly_blep()`], which needs to correct the sample before the reset as
+    /// well as the one after it -- so while any sync input is connected, the output is delayed by one sample.
+    /// 
+    /// [`Constant`]:       PitchMode::Constant
+    /// [`MIDI`]:           PitchMode::MIDI
+    /// [`Route`]:          synth::routing::Route
+    /// [`Route::apply()`]: synth::routing::Route::apply()
+    pub fn calc(&mut self) -> (f64, f64) {
         if self.bypass {
-            return 0.0;
+            return (0.0, 0.0);
         }
 
+        unsafe { self.pitch_controller.drift(&mut self.rng, 1.0 / synth::SAMPLE_RATE) };
         let mut frequency = self.pitch_controller.get_freq();
+        if self.env_target == EnvTarget::Pitch {
+            frequency *= f64::powf(2.0, self.env_amount * self.env_level * ENV_PITCH_RANGE / 12.0);
+        }
+        let table_length = TABLE_LENGTH as f64;
 
-        // get sample
-        let res = if self.waveform == Waveform::Noise {
-            Waveform::Noise.calc(0.0, 0.0)
-        } else {
-            self.waveform.get_sample(self.phase)
+        // for frequency modulation -- in Ratio mode, this is the amount (in table samples) that the lookup
+        // position is offset from each voice's phase
+        let mut phase_offset = 0.0;
+        match self.fm_mode {
+            FmMode::Linear => {
+                self.fm_in.iter().for_each(|input| frequency += self.fm_range as f64 * input.1);
+            }
+            FmMode::Ratio => {
+                frequency *= self.fm_ratio;
+                self.fm_in.iter().for_each(|input| phase_offset += self.fm_index * input.1);
+                phase_offset *= table_length / (2.0 * PI);
+            }
+        }
+
+        let mut pulse_width = self.pulse_width;
+        self.pwm_in.iter().for_each(|input| pulse_width += self.pwm_range * input.1);
+        if self.env_target == EnvTarget::PulseWidth {
+            pulse_width += 0.45 * self.env_amount * self.env_level;
+        }
+        let pulse_width = pulse_width.clamp(0.05, 0.95);
+
+        let increment = unsafe { frequency * table_length / synth::SAMPLE_RATE };
+        let mut morph = unsafe { self.morph.next(1.0 / synth::SAMPLE_RATE) };
+        if self.env_target == EnvTarget::Morph {
+            morph = (morph + self.env_amount * self.env_level).clamp(0.0, 1.0);
+        }
+        // noise, plucked strings, and samples are generated once, since each call advances their state, and played
+        // as a single centered voice -- summing identical copies would only make them louder
+        let generated_sample = match self.waveform {
+            Waveform::Noise => unsafe { self.noise.next(&mut self.rng, frequency, synth::SAMPLE_RATE) },
+            Waveform::Pluck => unsafe { self.pluck.next(frequency, synth::SAMPLE_RATE) },
+            Waveform::Sample => unsafe {
+                self.sampler.next(frequency, &self.pitch_controller.tuning, synth::SAMPLE_RATE)
+            },
+            _ => 0.0,
         };
+        let generated = matches!(self.waveform, Waveform::Noise | Waveform::Pluck | Waveform::Sample);
+
+        let sync = self.sync_pending.take();
+        // the BLEP corrections to apply to the delayed sample, before the sync reset:
+        let mut before_left = 0.0;
+        let mut before_right = 0.0;
+
+        let mut left = 0.0;
+        let mut right = 0.0;
+        let mut wrapped = None;
 
-        // for frequency modulation
-        self.fm_in.iter().for_each(|input| frequency += self.fm_range as f64 * input.1);
+        let voices = if generated { 1 } else { self.unison.voices() };
+        for (i, phase) in self.phases.iter_mut().enumerate().take(voices) {
+            let voice_increment = increment * self.unison.ratio(i);
+            let dt = voice_increment / table_length;
+            let (pan_left, pan_right) = if generated { (1.0, 1.0) } else { self.unison.pan(i) };
 
-        // iterate to next sample/phase
-        unsafe {
-            let table_length = TABLE_LENGTH as f64;
+            // hard sync: the reset happened `offset` samples ago, so restart the cycle that far along
+            let mut correction = 0.0;
+            if let (Some(offset), false) = (sync, generated) {
+                let reset_phase = (*phase - offset * voice_increment).rem_euclid(table_length);
+                let wavetable = match self.waveform {
+                    Waveform::Additive => Some(&*self.additive),
+                    _ => self.wavetable.as_deref(),
+                };
+                let step = naive_sample(&self.waveform, wavetable, morph, 0.0, pulse_width)
+                    - naive_sample(&self.waveform, wavetable, morph, reset_phase, pulse_width);
 
-            // frequency should not affect white noise
-            if self.waveform != Waveform::Noise {
-                self.phase += frequency * table_length / synth::SAMPLE_RATE;
+                let before = 0.5 * step * blep::poly_blep(1.0 - (1.0 - offset) * dt, dt);
+                before_left += before * pan_left;
+                before_right += before * pan_right;
+                correction = 0.5 * step * blep::poly_blep(offset * dt, dt);
+
+                *phase = offset * voice_increment;
             }
-            if self.phase >= table_length as f64 {
-                self.phase -= table_length as f64;
+
+            // self-feedback averages the voice's last two samples to keep high feedback amounts stable
+            let (last, second_last) = self.feedback_history[i];
+            let feedback_offset = self.feedback * 0.25 * (last + second_last) * table_length;
+            let lookup = (*phase + phase_offset + feedback_offset).rem_euclid(table_length);
+
+            // get sample
+            let res = match self.waveform {
+                Waveform::Noise | Waveform::Pluck | Waveform::Sample => generated_sample,
+                Waveform::Pulse => blep::pulse(lookup / table_length, dt, pulse_width),
+                Waveform::Additive => {
+                    let position = lookup / table_length;
+                    self.additive.sample(0, position, dt) * (1.0 - self.additive_fade)
+                        + self.additive_previous.sample(0, position, dt) * self.additive_fade
+                }
+                Waveform::Custom => self.wavetable.as_ref().map_or(0.0, |table| {
+                    table.morph(morph, lookup / table_length, dt)
+                }),
+                _ => self.waveform.get_sample(lookup),
+            } + correction;
+            self.feedback_history[i] = (res, last);
+
+            left += res * pan_left;
+            right += res * pan_right;
+
+            // iterate to next sample/phase
+            // noise, plucked strings, and samples don't use the phase
+            if !generated {
+                *phase += voice_increment;
+            }
+            if *phase >= table_length {
+                *phase -= table_length;
+                // the first voice decides when this Oscillator syncs others
+                if i == 0 && voice_increment > 0.0 {
+                    wrapped = Some(*phase / voice_increment);
+                }
             }
         }
 
-        res * self.gain
+        self.sync_out = wrapped;
+        self.additive_fade = unsafe { (self.additive_fade - 1.0 / (ADDITIVE_FADE * synth::SAMPLE_RATE)).max(0.0) };
+
+        // for amplitude and ring modulation
+        let mut modulation = 1.0;
+        self.am_in.iter().for_each(|input| modulation *= 1.0 - self.am_depth * (1.0 - input.1) / 2.0);
+        self.ring_in.iter().for_each(|input| modulation *= 1.0 - self.am_depth + self.am_depth * input.1);
+
+        let unison_gain = if generated { 1.0 } else { self.unison.gain() };
+        let envelope = if self.amp_enabled { self.amp.calc(1.0) } else { 1.0 };
+        let gain = self.gain * unison_gain * envelope;
+        let res = (left * gain * modulation, right * gain * modulation);
+
+        if self.sync_in.is_empty() {
+            res
+        } else {
+            let delayed = self.sync_delay;
+            self.sync_delay = res;
+            (delayed.0 + before_left * gain * modulation, delayed.1 + before_right * gain * modulation)
+        }
+    }
+
+    /// Returns where the last sample completed a cycle, if it did. See [`sync_sample_in()`].
+    /// 
+    /// [`sync_sample_in()`]:   Oscillator::sync_sample_in()
+    pub fn sync_out(&self) -> Option<f64> {
+        self.sync_out
     }
 
     /// Calls [`set_coarse()`] on `self.pitch_controller`.
@@ -111,30 +395,57 @@ impl Oscillator {
         self.pitch_controller.set_fine(fine);
     }
 
+    /// Calls [`set_drift()`] on `self.pitch_controller`.
+    /// 
+    /// [`set_drift()`]:    PitchController::set_drift()
+    pub fn set_drift(&mut self, drift: f64) {
+        self.pitch_controller.set_drift(drift);
+    }
+
+    /// Calls [`set_octave()`] on `self.pitch_controller`.
+    /// 
+    /// [`set_octave()`]:   PitchController::set_octave()
+    pub fn detune_octave(&mut self, octave: i32) {
+        self.pitch_controller.set_octave(octave);
+    }
+
     /// Returns the [`PitchMode`] of `self.pitch_controller`.
     pub fn get_mode(&self) -> PitchMode {
         self.pitch_controller.mode
     }
 
-    /// Returns the [`OutputMode`] of `self`.
-    pub fn get_output_mode(&self) -> OutputMode {
-        self.output_mode
-    }
-
     /// Sends a MIDI note-on signal to `self`.
     /// 
     /// Calls [`note_on()`] on `self.pitch_controller`. If there are no currently-held notes before this one,
-    /// `self.phase` is reset to 0 in an attempt to reduce audio popping by placing the next sample at a
-    /// zero-crossing.
+    /// the phases of the unison voices are reset according to `self.phase_mode` -- see [`PhaseMode`]. Every
+    /// note-on also restarts the envelope of `self.morph`, plucks `self.pluck` with a burst from `self.noise` if
+    /// the `Oscillator` is set to [`Waveform::Pluck`], and restarts `self.sampler` if it's set to
+    /// [`Waveform::Sample`].
     /// 
     /// This function assumes `self.pitch_controller.mode` has already been confirmed as [`PitchMode::MIDI`].
     /// 
     /// [`note_on()`]:  PitchController::note_on()
-    pub fn note_on(&mut self, pitch: u8) {
+    pub fn note_on(&mut self, pitch: u8, velocity: u8) {
         if self.pitch_controller.midi_notes.is_empty() {
-            self.phase = 0.0;
+            self.reset_phases();
         }
+        self.morph.restart();
+        self.amp.note_on(pitch, velocity);
         self.pitch_controller.note_on(pitch);
+
+        if self.waveform == Waveform::Pluck {
+            let mut frequency = self.pitch_controller.get_freq();
+            if self.fm_mode == FmMode::Ratio {
+                frequency *= self.fm_ratio;
+            }
+            let (noise, rng) = (&mut self.noise, &mut self.rng);
+            unsafe {
+                self.pluck.excite(frequency, synth::SAMPLE_RATE, || noise.next(rng, frequency, synth::SAMPLE_RATE));
+            }
+        }
+        if self.waveform == Waveform::Sample {
+            self.sampler.trigger();
+        }
     }
 
     /// Sends a MIDI note-off signal to `self`.
@@ -145,13 +456,60 @@ impl Oscillator {
     /// 
     /// [`note_off()`]: PitchController::note_off()
     pub fn note_off(&mut self, pitch: u8) {
+        self.amp.note_off(pitch);
+        self.pitch_controller.note_off(pitch);
+    }
+
+    /// Sends a MIDI note-on signal to `self.pitch_controller` and `self.amp` alone, for an `Oscillator` in
+    /// [`PitchMode::Constant`] that follows the keyboard by its key tracking amount. Unlike [`note_on()`], nothing
+    /// else is restarted.
+    /// 
+    /// [`note_on()`]:  Oscillator::note_on()
+    pub fn track_note_on(&mut self, pitch: u8, velocity: u8) {
+        self.amp.note_on(pitch, velocity);
+        self.pitch_controller.note_on(pitch);
+    }
+
+    /// Sends a MIDI note-off signal to `self.pitch_controller` and `self.amp` alone. See [`track_note_on()`].
+    /// 
+    /// [`track_note_on()`]:    Oscillator::track_note_on()
+    pub fn track_note_off(&mut self, pitch: u8) {
+        self.amp.note_off(pitch);
         self.pitch_controller.note_off(pitch);
     }
 
-    /// Inserts the `Oscillator` at `index` from the [`synth`] `oscs` vector if it doesn't already exist.
-    pub fn add_fm_in(&mut self, index: usize) {
-        if !self.fm_in.contains_key(&index) {
-            self.fm_in.insert(index, 0.0);
+    /// Connects the `Oscillator` at `index` from the [`synth`] `oscs` vector as an input that modulates `kind`,
+    /// if it isn't already.
+    pub fn add_input(&mut self, index: usize, kind: ModKind) {
+        match kind {
+            ModKind::Fm => { self.fm_in.entry(index).or_insert(0.0); }
+            ModKind::Pwm => { self.pwm_in.entry(index).or_insert(0.0); }
+            ModKind::Sync => { self.sync_in.insert(index); }
+            ModKind::Am => { self.am_in.entry(index).or_insert(1.0); }
+            ModKind::Ring => { self.ring_in.entry(index).or_insert(1.0); }
+        }
+    }
+
+    /// Disconnects the `Oscillator` at `index` as an input that modulates `kind`.
+    pub fn remove_input(&mut self, index: usize, kind: ModKind) {
+        match kind {
+            ModKind::Fm => { self.fm_in.remove(&index); }
+            ModKind::Pwm => { self.pwm_in.remove(&index); }
+            ModKind::Sync => {
+                self.sync_in.remove(&index);
+                if self.sync_in.is_empty() {
+                    self.sync_delay = (0.0, 0.0);
+                }
+            }
+            ModKind::Am => { self.am_in.remove(&index); }
+            ModKind::Ring => { self.ring_in.remove(&index); }
+        }
+    }
+
+    /// Inputs the latest sample generated by the AM input at `index` for `self`'s next calculation.
+    pub fn am_sample_in(&mut self, index: usize, sample_in: f64) {
+        if let Some(sample) = self.am_in.get_mut(&index) {
+            *sample = sample_in;
         }
     }
 
@@ -160,9 +518,40 @@ impl Oscillator {
         self.pitch_controller.pitch_bend(lsb, msb);
     }
 
-    /// Removes the `Oscillator` at `index` from `self.fm_in`.
-    pub fn remove_fm_in(&mut self, index: usize) {
-        self.fm_in.remove(&index);
+    /// Resets the phase of every unison voice according to `self.phase_mode`. See [`note_on()`].
+    /// 
+    /// [`note_on()`]:  Oscillator::note_on()
+    fn reset_phases(&mut self) {
+        match self.phase_mode {
+            PhaseMode::FreeRunning => (),
+            PhaseMode::Random => {
+                self.phases.iter_mut().for_each(|phase| {
+                    *phase = self.rng.next_f64() * TABLE_LENGTH as f64;
+                });
+            }
+            PhaseMode::Reset => {
+                // the other unison voices still start at random phases, so they don't reinforce each other at
+                // the start of the note
+                self.phases.iter_mut().skip(1).for_each(|phase| {
+                    *phase = self.rng.next_f64() * TABLE_LENGTH as f64;
+                });
+                self.phases[0] = self.start_phase / 360.0 * TABLE_LENGTH as f64;
+            }
+        }
+    }
+
+    /// Inputs the latest sample generated by the PWM input at `index` for `self`'s next calculation.
+    pub fn pwm_sample_in(&mut self, index: usize, sample_in: f64) {
+        if let Some(sample) = self.pwm_in.get_mut(&index) {
+            *sample = sample_in;
+        }
+    }
+
+    /// Inputs the latest `level` of the [`MultiStage`] envelope for `self`'s next calculation.
+    /// 
+    /// [`MultiStage`]: synth::multistage::MultiStage
+    pub fn env_sample_in(&mut self, level: f64) {
+        self.env_level = level;
     }
 
     /// Inputs the latest sample generated by the FM input at `index` for `self`'s next calculation.
@@ -172,6 +561,103 @@ impl Oscillator {
         }
     }
 
+    /// Restricts `depth` to the range `[0..1]`, then replaces `self.am_depth`.
+    pub fn set_am_depth(&mut self, depth: f64) {
+        self.am_depth = depth.clamp(0.0, 1.0);
+    }
+
+    /// Replaces the `attack` time (in seconds) of `self.amp`'s envelope.
+    pub fn set_amp_attack(&mut self, attack: f64) {
+        self.amp.adsr.attack = attack;
+    }
+
+    /// Replaces the `decay` time (in seconds) of `self.amp`'s envelope.
+    pub fn set_amp_decay(&mut self, decay: f64) {
+        self.amp.adsr.decay = decay;
+    }
+
+    /// Replaces `self.amp_enabled`, deciding whether `self.amp` shapes the output.
+    pub fn set_amp_enabled(&mut self, enabled: bool) {
+        self.amp_enabled = enabled;
+    }
+
+    /// Replaces the `release` time (in seconds) of `self.amp`'s envelope.
+    pub fn set_amp_release(&mut self, release: f64) {
+        self.amp.adsr.release = release;
+    }
+
+    /// Calls [`set_sustain()`] on `self.amp`'s envelope.
+    /// 
+    /// [`set_sustain()`]:  synth::amp::Envelope::set_sustain()
+    pub fn set_amp_sustain(&mut self, sustain: f64) {
+        self.amp.adsr.set_sustain(sustain);
+    }
+
+    /// Calls [`set_amount()`] on `self.morph`.
+    /// 
+    /// [`set_amount()`]:   Morph::set_amount()
+    pub fn set_morph_amount(&mut self, amount: f64) {
+        self.morph.set_amount(amount);
+    }
+
+    /// Calls [`set_attack()`] on `self.morph`.
+    /// 
+    /// [`set_attack()`]:   Morph::set_attack()
+    pub fn set_morph_attack(&mut self, attack: f64) {
+        self.morph.set_attack(attack);
+    }
+
+    /// Calls [`set_decay()`] on `self.morph`.
+    /// 
+    /// [`set_decay()`]:    Morph::set_decay()
+    pub fn set_morph_decay(&mut self, decay: f64) {
+        self.morph.set_decay(decay);
+    }
+
+    /// Calls [`set_position()`] on `self.morph`.
+    /// 
+    /// [`set_position()`]: Morph::set_position()
+    pub fn set_morph_position(&mut self, position: f64) {
+        self.morph.set_position(position);
+    }
+
+    /// Calls [`set_sustain()`] on `self.morph`.
+    /// 
+    /// [`set_sustain()`]:  Morph::set_sustain()
+    pub fn set_morph_sustain(&mut self, sustain: f64) {
+        self.morph.set_sustain(sustain);
+    }
+
+    /// Restricts `amount` to the range `[-1..1]`, then replaces `self.env_amount`.
+    pub fn set_env_amount(&mut self, amount: f64) {
+        self.env_amount = amount.clamp(-1.0, 1.0);
+    }
+
+    /// Replaces `self.env_target` with `target`.
+    pub fn set_env_target(&mut self, target: EnvTarget) {
+        self.env_target = target;
+    }
+
+    /// Restricts `feedback` to the range `[0..1]`, then replaces `self.feedback`.
+    pub fn set_feedback(&mut self, feedback: f64) {
+        self.feedback = feedback.clamp(0.0, 1.0);
+    }
+
+    /// Restricts `index` to the range `[0..10]`, then replaces `self.fm_index`.
+    pub fn set_fm_index(&mut self, index: f64) {
+        self.fm_index = index.clamp(0.0, 10.0);
+    }
+
+    /// Replaces `self.fm_mode` with `mode`.
+    pub fn set_fm_mode(&mut self, mode: FmMode) {
+        self.fm_mode = mode;
+    }
+
+    /// Restricts `ratio` to the range `[0.5..16]`, then replaces `self.fm_ratio`.
+    pub fn set_fm_ratio(&mut self, ratio: f64) {
+        self.fm_ratio = ratio.clamp(0.5, 16.0);
+    }
+
     /// Sets the `range` of possible modulation to `self.frequency` by the FM input.
     pub fn set_fm_range(&mut self, range: u16) {
         self.fm_range = range;
@@ -184,6 +670,29 @@ impl Oscillator {
         self.pitch_controller.set_freq(freq);
     }
 
+    /// Calls [`set_frequency()`] on the [`Tuning`] of `self.pitch_controller` for each note in `notes`.
+    /// 
+    /// [`set_frequency()`]:    Tuning::set_frequency()
+    pub fn retune(&mut self, notes: &[(u8, f64)]) {
+        for &(note, frequency) in notes {
+            self.pitch_controller.tuning.set_frequency(note, frequency);
+        }
+    }
+
+    /// Calls [`set_tuning()`] on `self.pitch_controller`.
+    /// 
+    /// [`set_tuning()`]:   PitchController::set_tuning()
+    pub fn set_tuning(&mut self, tuning: &Tuning) {
+        self.pitch_controller.set_tuning(tuning);
+    }
+
+    /// Calls [`set_key_track()`] on `self.pitch_controller`.
+    /// 
+    /// [`set_key_track()`]:    PitchController::set_key_track()
+    pub fn set_key_track(&mut self, key_track: f64) {
+        self.pitch_controller.set_key_track(key_track);
+    }
+
     /// Modifies the `gain` property of `self`.
     /// 
     /// The value of the `gain_db` argument should be measured in dB. Often this value is between -60 and 0.
@@ -197,9 +706,175 @@ impl Oscillator {
         self.pitch_controller.set_mode(mode);
     }
 
-    /// Replaces `self.output_mode` with `output`.
-    pub fn set_output(&mut self, output: OutputMode) {
-        self.output_mode = output;
+    /// Inputs the latest sample generated by the ring modulation input at `index` for `self`'s next calculation.
+    pub fn ring_sample_in(&mut self, index: usize, sample_in: f64) {
+        if let Some(sample) = self.ring_in.get_mut(&index) {
+            *sample = sample_in;
+        }
+    }
+
+    /// Inputs the result of [`sync_out()`] from the sync input at `index`. If it completed a cycle, `self` will
+    /// reset its phase on its next calculation.
+    /// 
+    /// [`sync_out()`]: Oscillator::sync_out()
+    pub fn sync_sample_in(&mut self, index: usize, sync: Option<f64>) {
+        if let (true, Some(offset)) = (self.sync_in.contains(&index), sync) {
+            // if several inputs reset this Oscillator at once, follow whichever reset happened earliest:
+            self.sync_pending = Some(self.sync_pending.map_or(offset, |pending| pending.max(offset)));
+        }
+    }
+
+    /// Restricts `width` to the range `[0.05..0.95]`, then replaces `self.pulse_width`.
+    pub fn set_pulse_width(&mut self, width: f64) {
+        self.pulse_width = width.clamp(0.05, 0.95);
+    }
+
+    /// Restricts `range` to the range `[0..0.45]`, then replaces `self.pwm_range`.
+    pub fn set_pwm_range(&mut self, range: f64) {
+        self.pwm_range = range.clamp(0.0, 0.45);
+    }
+
+    /// Calls [`set_curve()`] on `self.unison`.
+    /// 
+    /// [`set_curve()`]:    Unison::set_curve()
+    pub fn set_unison_curve(&mut self, curve: DetuneCurve) {
+        self.unison.set_curve(curve);
+    }
+
+    /// Calls [`set_detune()`] on `self.unison`.
+    /// 
+    /// [`set_detune()`]:   Unison::set_detune()
+    pub fn set_unison_detune(&mut self, detune: f64) {
+        self.unison.set_detune(detune);
+    }
+
+    /// Calls [`set_spread()`] on `self.unison`.
+    /// 
+    /// [`set_spread()`]:   Unison::set_spread()
+    pub fn set_unison_spread(&mut self, spread: f64) {
+        self.unison.set_spread(spread);
+    }
+
+    /// Calls [`set_voices()`] on `self.unison`. Any newly-added voices start at a random phase.
+    /// 
+    /// [`set_voices()`]:   Unison::set_voices()
+    pub fn set_unison_voices(&mut self, voices: usize) {
+        let old_voices = self.unison.voices();
+        self.unison.set_voices(voices);
+
+        for phase in self.phases.iter_mut().take(self.unison.voices()).skip(old_voices) {
+            *phase = self.rng.next_f64() * TABLE_LENGTH as f64;
+        }
+    }
+
+    /// Calls [`set_brightness()`] on `self.pluck`.
+    /// 
+    /// [`set_brightness()`]:   Pluck::set_brightness()
+    pub fn set_pluck_brightness(&mut self, brightness: f64) {
+        self.pluck.set_brightness(brightness);
+    }
+
+    /// Calls [`set_damping()`] on `self.pluck`.
+    /// 
+    /// [`set_damping()`]:  Pluck::set_damping()
+    pub fn set_pluck_damping(&mut self, damping: f64) {
+        self.pluck.set_damping(damping);
+    }
+
+    /// Calls [`set_position()`] on `self.pluck`.
+    /// 
+    /// [`set_position()`]: Pluck::set_position()
+    pub fn set_pluck_position(&mut self, position: f64) {
+        self.pluck.set_position(position);
+    }
+
+    /// Calls [`set_loop_end()`] on `self.sampler`.
+    /// 
+    /// [`set_loop_end()`]: Sampler::set_loop_end()
+    pub fn set_sample_loop_end(&mut self, end: f64) {
+        self.sampler.set_loop_end(end);
+    }
+
+    /// Calls [`set_loop_mode()`] on `self.sampler`.
+    /// 
+    /// [`set_loop_mode()`]:    Sampler::set_loop_mode()
+    pub fn set_sample_loop_mode(&mut self, mode: LoopMode) {
+        self.sampler.set_loop_mode(mode);
+    }
+
+    /// Calls [`set_loop_start()`] on `self.sampler`.
+    /// 
+    /// [`set_loop_start()`]:   Sampler::set_loop_start()
+    pub fn set_sample_loop_start(&mut self, start: f64) {
+        self.sampler.set_loop_start(start);
+    }
+
+    /// Calls [`set_root()`] on `self.sampler`.
+    /// 
+    /// [`set_root()`]: Sampler::set_root()
+    pub fn set_sample_root(&mut self, root: u8) {
+        self.sampler.set_root(root);
+    }
+
+    /// Calls [`set_start()`] on `self.sampler`.
+    /// 
+    /// [`set_start()`]:    Sampler::set_start()
+    pub fn set_sample_start(&mut self, start: f64) {
+        self.sampler.set_start(start);
+    }
+
+    /// Calls [`set_color()`] on `self.noise`.
+    /// 
+    /// [`set_color()`]:    Noise::set_color()
+    pub fn set_noise_color(&mut self, color: NoiseColor) {
+        self.noise.set_color(color);
+    }
+
+    /// Calls [`set_key_track()`] on `self.noise`.
+    /// 
+    /// [`set_key_track()`]:    Noise::set_key_track()
+    pub fn set_noise_key_track(&mut self, key_track: bool) {
+        self.noise.set_key_track(key_track);
+    }
+
+    /// Restarts `self.rng` from `seed`, so that the noise and random phases that follow are the same every time
+    /// the same seed is set.
+    pub fn set_noise_seed(&mut self, seed: u64) {
+        self.rng = Prng::new(seed);
+    }
+
+    /// Replaces `self.additive` with `additive`, starting a crossfade from the current table. Returns the table
+    /// that was faded out before this one, so that it can be dropped without holding a lock the audio thread
+    /// waits on.
+    pub fn set_additive(&mut self, additive: Arc<Wavetable>) -> Arc<Wavetable> {
+        let previous = std::mem::replace(&mut self.additive, additive);
+        // if the last crossfade hasn't finished, this jumps straight to the latest two tables -- a small
+        // discontinuity, but much smaller than switching without a fade
+        self.additive_fade = 1.0;
+        std::mem::replace(&mut self.additive_previous, previous)
+    }
+
+    /// Calls [`set_sample()`] on `self.sampler`, returning the `Sample` it replaced.
+    /// 
+    /// [`set_sample()`]:   Sampler::set_sample()
+    pub fn set_sample(&mut self, sample: Arc<Sample>) -> Option<Arc<Sample>> {
+        self.sampler.set_sample(sample)
+    }
+
+    /// Replaces `self.wavetable` with `wavetable`, which is played while `self.waveform` is
+    /// [`Waveform::Custom`].
+    pub fn set_wavetable(&mut self, wavetable: Arc<Wavetable>) {
+        self.wavetable = Some(wavetable);
+    }
+
+    /// Replaces `self.phase_mode` with `mode`.
+    pub fn set_phase_mode(&mut self, mode: PhaseMode) {
+        self.phase_mode = mode;
+    }
+
+    /// Restricts `phase` (in degrees) to the range `[0..360]`, then replaces `self.start_phase`.
+    pub fn set_start_phase(&mut self, phase: f64) {
+        self.start_phase = phase.clamp(0.0, 360.0);
     }
 
     /// Replaces `self.waveform` with `waveform`.
@@ -208,8 +883,73 @@ impl Oscillator {
     }
 }
 
+#[cfg(test)]
+impl Oscillator {
+    /// Returns an `Oscillator` that isn't bypassed, held at a constant `freq` regardless of MIDI input.
+    fn running(freq: f64) -> Oscillator {
+        let mut osc = Oscillator::new();
+        osc.bypass = false;
+        osc.set_mode(PitchMode::Constant);
+        osc.set_freq(freq);
+        osc
+    }
+}
+
+
+/// Returns the amplitude of `waveform` at `phase` without any band-limiting, which is used to measure the size
+/// of the jump caused by a hard sync reset. Additive and custom waveforms are read from the full-bandwidth copy of
+/// `wavetable`, at the point `morph` between its frames.
+fn naive_sample(waveform: &Waveform, wavetable: Option<&Wavetable>, morph: f64, phase: f64, pulse_width: f64) -> f64 {
+    match waveform {
+        Waveform::Additive | Waveform::Custom => {
+            wavetable.map_or(0.0, |table| table.morph(morph, phase / TABLE_LENGTH as f64, 0.0))
+        }
+        Waveform::Noise | Waveform::Pluck | Waveform::Sample => 0.0,
+        Waveform::Pulse => blep::pulse(phase / TABLE_LENGTH as f64 % 1.0, 0.0, pulse_width),
+        _ => waveform.get_sample(phase),
+    }
+}
+
+/// The property of an [`Oscillator`] that the [`MultiStage`] envelope modulates.
+/// 
+/// [`MultiStage`]: synth::multistage::MultiStage
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum EnvTarget {
+    /// The envelope raises (or lowers, with a negative amount) the pitch, by up to [`ENV_PITCH_RANGE`] semitones.
+    Pitch,
+    /// The envelope widens (or narrows) the pulse width of [`Waveform::Pulse`].
+    PulseWidth,
+    /// The envelope moves the position read from within a multi-frame [`Wavetable`], on top of the [`Morph`].
+    Morph,
+}
+
+/// The way in which FM inputs modify an [`Oscillator`].
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum FmMode {
+    /// FM inputs add to the [`Oscillator`]'s frequency, by up to its `fm_range` in Hz. Because the range is
+    /// constant, the timbre changes as the pitch moves up the keyboard.
+    Linear,
+    /// The [`Oscillator`]'s frequency is a ratio of the MIDI note, and FM inputs modulate its phase by an index
+    /// relative to its own frequency, so the timbre is consistent across the keyboard.
+    Ratio,
+}
+
+/// What happens to the phase of an [`Oscillator`]'s unison voices when a note starts while no other notes are
+/// held.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum PhaseMode {
+    /// The phase never resets, so the waveform carries on from wherever it was, like an analog oscillator.
+    /// Useful for pads, where the same attack on every note can sound mechanical.
+    FreeRunning,
+    /// Every voice starts at a random phase.
+    Random,
+    /// The first voice restarts at the `Oscillator`'s start phase, so every note has the same attack. Useful
+    /// for plucks and basses. Any other unison voices start at random phases.
+    Reset,
+}
 
 /// The mode in which an [`Oscillator`]'s [`PitchController`] should operate.
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone,Copy,Debug,PartialEq)]
 pub enum PitchMode {
     /// The [`Oscillator`] will continuously produce a tone at a constant frequency.
@@ -218,22 +958,24 @@ pub enum PitchMode {
     MIDI,
 }
 
-/// The target for an [`Oscillator`]'s output signal.
-#[derive(Clone,Copy,Debug)]
-pub enum OutputMode {
-    /// The [`Oscillator`]'s audio is sent to the [`Mixer`].
-    /// 
-    /// [`Mixer`]:  crate::mixer::Mixer
-    Master,
-    /// The [`Oscillator`]'s signal modifies the frequency of another [`Oscillator`].
-    Osc(usize),
-}
-
 /// Abstracts the implementation of an [`Oscillator`]'s frequency, the various ways it can be modified
 /// by other parameters, and the calculations needed to maintain accurate information to generate audio samples.
 struct PitchController {
-    /// The most recently set base frequency for the [`Oscillator`], without modulations.
+    /// The most recently set base frequency for the [`Oscillator`] in [`PitchMode::Constant`], without
+    /// modulations.
     base_frequency: f64,
+    /// How far (in cents) `[0..50]` the pitch can drift away from its set value, wandering slowly at random like
+    /// an analog oscillator.
+    drift: f64,
+    /// The point `[-1..1]` the drift is currently wandering towards.
+    drift_target: f64,
+    /// The time (in seconds) until the drift picks a new `drift_target`.
+    drift_timer: f64,
+    /// The current drift `[-1..1]`, as a fraction of `self.drift`.
+    drift_value: f64,
+    /// How closely `[0..1]` the frequency follows MIDI notes in [`PitchMode::Constant`], relative to A4 (MIDI
+    /// pitch 69). At 0 the frequency is fixed, and at 1 it moves by a semitone with each key.
+    key_track: f64,
     /// When in [`PitchMode::MIDI`] and all notes have been released, this field retains the value of the
     /// last-released pitch.
     last_pitch: u8,
@@ -242,12 +984,16 @@ struct PitchController {
     midi_notes: Vec<u8>,
     /// The [`PitchMode`] that describes where pitch information should be sourced from.
     mode: PitchMode,
-    /// An amount of semitones in the range `[-12..12]` by which to adjust incoming MIDI pitches.
+    /// An amount of octaves in the range `[-4..4]` by which to adjust incoming MIDI pitches.
+    octave: i8,
+    /// An amount of semitones in the range `[-48..48]` by which to adjust incoming MIDI pitches.
     offset_coarse: i8,
-    /// An amount of cents in the range `[-0.5..0.5]` by which to adjust incoming MIDI pitches.
+    /// An amount of cents in the range `[-100..100]` by which to adjust the pitch, in either [`PitchMode`].
     offset_fine: f64,
     /// The amount of pitch bend, controlled by a MIDI controller, in the range `[-1.0..1.0]`.
     pitch_bend: f64,
+    /// The [`Tuning`] giving the frequency of each MIDI note.
+    tuning: Tuning,
 }
 
 impl PitchController {
@@ -258,12 +1004,19 @@ impl PitchController {
     fn new() -> PitchController {
         PitchController {
             base_frequency: 440.0,
+            drift: 0.0,
+            drift_target: 0.0,
+            drift_timer: 0.0,
+            drift_value: 0.0,
+            key_track: 0.0,
             last_pitch: 69,
             midi_notes: vec![],
             mode: PitchMode::MIDI,
+            octave: 0,
             offset_coarse: 0,
             offset_fine: 0.0,
             pitch_bend: 0.0,
+            tuning: Tuning::default(),
         }
     }
 
@@ -271,30 +1024,47 @@ impl PitchController {
     /// 
     /// Called from [`Oscillator::calc()`]. 
     fn get_freq(&self) -> f64 {
+        let note = self.midi_notes.first().copied().unwrap_or(self.last_pitch) as i32;
+        let cents = self.offset_fine + self.drift * self.drift_value;
+
         let freq = if self.mode == PitchMode::MIDI {
-            let pitch = if !self.midi_notes.is_empty() {
-                self.midi_notes[0] as i8 + self.offset_coarse
-            } else {
-                self.last_pitch as i8 + self.offset_coarse
-            };
+            let pitch = note + 12 * self.octave as i32 + self.offset_coarse as i32;
 
-            if self.offset_fine != 0.0 {
-                440.0 * f64::powf(2.0, (pitch as f64 + self.offset_fine - 69.0) / 12.0)
+            if cents != 0.0 {
+                self.tuning.frequency(pitch) * f64::powf(2.0, cents / 1200.0)
             } else {
-                MIDI_TO_HZ[(pitch as usize).max(0).min(127)]
+                self.tuning.frequency(pitch)
             }
         } else {
-            self.base_frequency
+            // key tracking follows the tuning too, so at 100% the keyboard plays the same intervals as MIDI mode
+            let tracked = f64::powf(self.tuning.frequency(note) / self.tuning.frequency(69), self.key_track);
+            // the octave and coarse detune transpose the fixed frequency by equal-tempered semitones
+            let semitones = 12.0 * self.octave as f64 + self.offset_coarse as f64;
+            self.base_frequency * tracked * f64::powf(2.0, (100.0 * semitones + cents) / 1200.0)
         };
 
         freq * f64::powf(2_f64, self.pitch_bend * 2_f64 / 12_f64)
     }
 
+    /// Advances the drift by `dt` seconds, gliding towards a new random target from `rng` every
+    /// [`DRIFT_INTERVAL`]. Does nothing (and leaves `rng` untouched) while `self.drift` is 0.
+    fn drift(&mut self, rng: &mut Prng, dt: f64) {
+        if self.drift == 0.0 {
+            return;
+        }
+
+        self.drift_timer -= dt;
+        if self.drift_timer <= 0.0 {
+            self.drift_timer += DRIFT_INTERVAL;
+            self.drift_target = rng.next_f64() * 2.0 - 1.0;
+        }
+        self.drift_value += (self.drift_target - self.drift_value) * (dt / DRIFT_INTERVAL).min(1.0);
+    }
+
     /// Sends the MIDI NoteOn signal to the `PitchController`.
     fn note_on(&mut self, pitch: u8) {
         if !self.midi_notes.contains(&pitch) {
             self.midi_notes.insert(0, pitch);
-            self.base_frequency = MIDI_TO_HZ[pitch as usize];
         }
     }
 
@@ -306,9 +1076,7 @@ impl PitchController {
                 break;
             }
         }
-        if !self.midi_notes.is_empty() {
-            self.base_frequency = MIDI_TO_HZ[self.midi_notes[0] as usize];
-        } else {
+        if self.midi_notes.is_empty() {
             self.last_pitch = pitch;
         }
     }
@@ -323,15 +1091,20 @@ impl PitchController {
         self.pitch_bend = (pitch_bend as f64 - 8192_f64) / 8191_f64;
     }
 
-    /// Restricts `coarse` to the range `[-12..12]`, then replaces `self.offset_coarse`.
+    /// Restricts `coarse` to the range `[-48..48]`, then replaces `self.offset_coarse`.
     fn set_coarse(&mut self, coarse: i32) {
-        let coarse = coarse.max(-12).min(12) as i8;
+        let coarse = coarse.clamp(-48, 48) as i8;
         self.offset_coarse = coarse;
     }
 
-    /// Restricts `fine` to the range `[-0.5..0.5]`, then replaces `self.offset_fine`.
+    /// Restricts `drift` (in cents) to the range `[0..50]`, then replaces `self.drift`.
+    fn set_drift(&mut self, drift: f64) {
+        self.drift = drift.clamp(0.0, 50.0);
+    }
+
+    /// Restricts `fine` (in cents) to the range `[-100..100]`, then replaces `self.offset_fine`.
     fn set_fine(&mut self, fine: f64) {
-        let fine = fine.max(-0.5).min(0.5);
+        let fine = fine.clamp(-100.0, 100.0);
         self.offset_fine = fine;
     }
 
@@ -339,6 +1112,21 @@ impl PitchController {
         self.base_frequency = freq;
     }
 
+    /// Replaces `self.tuning` with a copy of `tuning`, without allocating.
+    fn set_tuning(&mut self, tuning: &Tuning) {
+        self.tuning.clone_from(tuning);
+    }
+
+    /// Restricts `key_track` to the range `[0..1]`, then replaces `self.key_track`.
+    fn set_key_track(&mut self, key_track: f64) {
+        self.key_track = key_track.clamp(0.0, 1.0);
+    }
+
+    /// Restricts `octave` to the range `[-4..4]`, then replaces `self.octave`.
+    fn set_octave(&mut self, octave: i32) {
+        self.octave = octave.clamp(-4, 4) as i8;
+    }
+
     fn set_mode(&mut self, mode: PitchMode) {
         self.mode = mode;
     }
@@ -359,15 +1147,87 @@ mod pitch_control_tests {
     }
 
     #[test]
-    fn constant_mode_ignores_pitch_detune() {
+    fn constant_mode_applies_octave_and_coarse_detune() {
         let mut pitch_controller = PitchController::new();
         pitch_controller.set_coarse(12);
-        pitch_controller.set_fine(-0.25);
+        pitch_controller.set_octave(2);
         pitch_controller.set_mode(PitchMode::Constant);
+        assert!((pitch_controller.get_freq() - 3520.0).abs() < 1e-9);
 
-        let freq = pitch_controller.get_freq();
+        pitch_controller.set_coarse(-7);
+        pitch_controller.set_octave(0);
+        assert!((pitch_controller.get_freq() - 440.0 * f64::powf(2.0, -7.0 / 12.0)).abs() < 1e-9);
+    }
 
-        assert_eq!(freq, 440.0)
+    #[test]
+    fn fine_tune_is_in_cents() {
+        let mut pitch_controller = PitchController::new();
+        pitch_controller.set_fine(100.0);
+        assert!((pitch_controller.get_freq() - synth::MIDI_TO_HZ[70]).abs() < 1e-9);
+
+        pitch_controller.set_fine(-1200.0);
+        assert!((pitch_controller.get_freq() - synth::MIDI_TO_HZ[68]).abs() < 1e-9);
+
+        // fine tune also applies to a constant frequency
+        pitch_controller.set_mode(PitchMode::Constant);
+        pitch_controller.set_freq(100.0);
+        pitch_controller.set_fine(50.0);
+        assert!((pitch_controller.get_freq() - 100.0 * f64::powf(2.0, 1.0 / 24.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn drift_wanders_slowly_within_its_amount() {
+        let mut rng = Prng::new(noise::DEFAULT_SEED);
+        let mut pitch_controller = PitchController::new();
+        pitch_controller.set_drift(20.0);
+
+        // 20 cents either side of 440Hz:
+        let (low, high) = (440.0 * f64::powf(2.0, -0.2 / 12.0), 440.0 * f64::powf(2.0, 0.2 / 12.0));
+        let mut last = pitch_controller.get_freq();
+        let mut moved = false;
+
+        for _ in 0..96000 {
+            pitch_controller.drift(&mut rng, 1.0 / 48000.0);
+            let freq = pitch_controller.get_freq();
+
+            assert!((low..=high).contains(&freq));
+            assert!((freq - last).abs() < 0.001);
+            moved |= freq != last;
+            last = freq;
+        }
+
+        assert!(moved);
+    }
+
+    #[test]
+    fn octave_and_coarse_transpose_midi_notes() {
+        let mut pitch_controller = PitchController::new();
+        pitch_controller.set_octave(-2);
+        pitch_controller.set_coarse(36);
+        pitch_controller.note_on(57);
+
+        // 57 - 24 + 36 = 69
+        assert_eq!(pitch_controller.get_freq(), 440.0);
+
+        pitch_controller.set_octave(-9);
+        pitch_controller.set_coarse(-60);
+        assert_eq!(pitch_controller.get_freq(), synth::MIDI_TO_HZ[0]);
+    }
+
+    #[test]
+    fn constant_mode_follows_key_track() {
+        let mut pitch_controller = PitchController::new();
+        pitch_controller.set_mode(PitchMode::Constant);
+        pitch_controller.set_freq(100.0);
+        pitch_controller.note_on(81);
+
+        assert_eq!(pitch_controller.get_freq(), 100.0);
+
+        pitch_controller.set_key_track(0.5);
+        assert!((pitch_controller.get_freq() - 100.0 * f64::sqrt(2.0)).abs() < 1e-9);
+
+        pitch_controller.set_key_track(1.0);
+        assert!((pitch_controller.get_freq() - 200.0).abs() < 1e-9);
     }
 
     #[test]
@@ -387,4 +1247,251 @@ mod pitch_control_tests {
         let res = pitch_controller.pitch_bend;
         assert_eq!(res, 1_f64);
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod sync_tests {
+    use super::*;
+
+    #[test]
+    fn sync_resets_phase_when_input_wraps() {
+        let mut master = Oscillator::running(100.0);
+        let mut slave = Oscillator::running(150.0);
+        slave.add_input(0, ModKind::Sync);
+
+        let mut resets = 0;
+        for _ in 0..48000 {
+            master.calc();
+            slave.sync_sample_in(0, master.sync_out());
+            let synced = slave.sync_pending.is_some();
+            slave.calc();
+
+            if synced {
+                resets += 1;
+                // after the reset, the slave should be less than one sample's movement into its cycle
+                let increment = unsafe { 150.0 * TABLE_LENGTH as f64 / synth::SAMPLE_RATE };
+                assert!(slave.phases[0] < 2.0 * increment);
+            }
+        }
+
+        assert!((99..=100).contains(&resets));
+    }
+
+    #[test]
+    fn synced_narrow_pulse_stays_continuous() {
+        // the largest jump between two samples of `slave`, synced to `master` when given
+        let largest_jump = |mut master: Option<&mut Oscillator>, slave: &mut Oscillator| {
+            let mut last = 0.0;
+            let mut largest: f64 = 0.0;
+            for i in 0..4800 {
+                if let Some(master) = master.as_deref_mut() {
+                    master.calc();
+                    slave.sync_sample_in(0, master.sync_out());
+                }
+                let (left, _) = slave.calc();
+                if i > 0 {
+                    largest = largest.max((left - last).abs());
+                }
+                last = left;
+            }
+            largest
+        };
+        let pulse = || {
+            let mut osc = Oscillator::running(150.0);
+            osc.set_waveform(Waveform::Pulse);
+            osc.set_pulse_width(0.25);
+            osc
+        };
+
+        let mut master = Oscillator::running(100.0);
+        let mut synced = pulse();
+        synced.add_input(0, ModKind::Sync);
+
+        // each reset lands on the low half of the cycle, jumping back up like the pulse's own rising edge, which
+        // the BLEP correction should smooth just as much
+        let free = largest_jump(None, &mut pulse());
+        let synced = largest_jump(Some(&mut master), &mut synced);
+        assert!(synced < free + 1e-6);
+    }
+}
+
+#[cfg(test)]
+mod fm_tests {
+    use super::*;
+
+    /// Returns a sine `Oscillator` at a constant 100Hz in `FmMode::Ratio`.
+    fn ratio() -> Oscillator {
+        let mut osc = Oscillator::running(100.0);
+        osc.set_fm_mode(FmMode::Ratio);
+        osc
+    }
+
+    #[test]
+    fn ratio_multiplies_frequency() {
+        let mut osc = ratio();
+        osc.set_fm_ratio(3.0);
+        (0..10).for_each(|_| { osc.calc(); });
+
+        let increment = unsafe { 300.0 * TABLE_LENGTH as f64 / synth::SAMPLE_RATE };
+        assert!((osc.phases[0] - 10.0 * increment).abs() < 1e-9);
+    }
+
+    #[test]
+    fn ratio_fm_offsets_phase_not_frequency() {
+        let mut plain = ratio();
+        let mut modulated = ratio();
+        modulated.add_input(1, ModKind::Fm);
+
+        let mut difference: f64 = 0.0;
+        for _ in 0..100 {
+            modulated.fm_sample_in(1, 0.5);
+            let (plain_left, _) = plain.calc();
+            let (modulated_left, _) = modulated.calc();
+            difference = difference.max((plain_left - modulated_left).abs());
+        }
+
+        // the modulated Oscillator sounds different, but moves through its cycle at the same speed:
+        assert!(difference > 0.1);
+        assert_eq!(modulated.phases[0], plain.phases[0]);
+    }
+
+    #[test]
+    fn zero_feedback_matches_plain_waveform() {
+        let mut osc = ratio();
+        osc.set_feedback(0.0);
+        for _ in 0..100 {
+            let expected = Waveform::Sine.get_sample(osc.phases[0]);
+            let (left, right) = osc.calc();
+            assert!((left - expected).abs() < 1e-12);
+            assert!((right - expected).abs() < 1e-12);
+        }
+
+        let mut feedback = ratio();
+        feedback.set_feedback(0.5);
+        let mut plain = ratio();
+        let difference = (0..100).map(|_| (feedback.calc().0 - plain.calc().0).abs()).fold(0.0, f64::max);
+        assert!(difference > 0.01);
+    }
+}
+
+#[cfg(test)]
+mod phase_tests {
+    use super::*;
+
+    /// Returns an `Oscillator` at 100Hz in `mode`, after running for 100 samples.
+    fn advanced(mode: PhaseMode) -> Oscillator {
+        let mut osc = Oscillator::running(100.0);
+        osc.set_phase_mode(mode);
+        (0..100).for_each(|_| { osc.calc(); });
+        osc
+    }
+
+    #[test]
+    fn reset_starts_at_start_phase() {
+        let mut osc = advanced(PhaseMode::Reset);
+        osc.set_start_phase(90.0);
+        osc.note_on(60, 127);
+
+        assert_eq!(osc.phases[0], TABLE_LENGTH as f64 / 4.0);
+    }
+
+    #[test]
+    fn free_running_never_resets() {
+        let mut osc = advanced(PhaseMode::FreeRunning);
+        let phase = osc.phases[0];
+        osc.note_on(60, 127);
+
+        assert!(phase > 0.0);
+        assert_eq!(osc.phases[0], phase);
+    }
+
+    #[test]
+    fn random_phases_differ_between_notes() {
+        let mut osc = advanced(PhaseMode::Random);
+        osc.note_on(60, 127);
+        let first = osc.phases[0];
+        osc.note_off(60);
+        osc.note_on(60, 127);
+
+        assert_ne!(osc.phases[0], first);
+    }
+}
+
+#[cfg(test)]
+mod amp_tests {
+    use super::*;
+
+    /// Returns the peak amplitude of `osc` over the next `length` samples.
+    fn peak(osc: &mut Oscillator, length: usize) -> f64 {
+        (0..length).map(|_| osc.calc().0.abs()).fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn own_envelope_shapes_output() {
+        let mut osc = Oscillator::running(100.0);
+        osc.set_waveform(Waveform::Square);
+        osc.set_amp_enabled(true);
+        osc.set_amp_release(0.01);
+
+        // silent until a note starts:
+        assert_eq!(peak(&mut osc, 480), 0.0);
+        osc.note_on(60, 127);
+        assert!(peak(&mut osc, 480) > 0.5);
+
+        // and once its release has finished:
+        osc.note_off(60);
+        peak(&mut osc, 960);
+        assert_eq!(peak(&mut osc, 480), 0.0);
+
+        // without it, the output doesn't follow notes:
+        osc.set_amp_enabled(false);
+        assert!(peak(&mut osc, 480) > 0.5);
+    }
+}
+
+#[cfg(test)]
+mod generated_tests {
+    use super::*;
+
+    #[test]
+    fn unison_spread_leaves_noise_centered() {
+        let mut osc = Oscillator::new();
+        osc.bypass = false;
+        osc.set_waveform(Waveform::Noise);
+        osc.set_unison_voices(16);
+        osc.set_unison_spread(1.0);
+
+        for _ in 0..480 {
+            let (left, right) = osc.calc();
+            assert_eq!(left, right);
+        }
+    }
+
+    #[test]
+    fn level_ignores_unison() {
+        // the RMS level of `osc` over the first 4800 samples of a note, with `voices` unison voices
+        let level = |mut osc: Oscillator, voices| {
+            osc.set_unison_voices(voices);
+            // the new voices draw their random phases first, so the seed is set after them to give both the same
+            // noise and pluck excitation
+            osc.set_noise_seed(1);
+            osc.note_on(57, 127);
+            let sum: f64 = (0..4800).map(|_| osc.calc().0.powi(2)).sum();
+            (sum / 4800.0).sqrt()
+        };
+
+        for waveform in [Waveform::Noise, Waveform::Pluck, Waveform::Sample] {
+            let playing = || {
+                let mut osc = Oscillator::running(220.0);
+                osc.set_waveform(waveform.clone());
+                let sine = (0..4800).map(|i| (i as f32 * 0.05).sin()).collect();
+                osc.set_sample(Arc::new(Sample::from_samples(sine, 48000.0)));
+                osc
+            };
+
+            let single = level(playing(), 1);
+            assert!(single > 0.01);
+            assert!((level(playing(), 16) - single).abs() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,200 @@
+/// The number of samples in a [`Pluck`]'s delay line, which decides the lowest note it can play (about 12Hz at a
+/// sample rate of 48kHz).
+const BUFFER_LENGTH: usize = 4096;
+
+/// The decay time (in seconds, to -60dB) of a [`Pluck`] with no damping.
+const MAX_DECAY: f64 = 10.0;
+
+/// A Karplus-Strong plucked string model, used by [`Waveform::Pluck`].
+/// 
+/// A burst of noise (the "pluck") is written into a delay line one period long, which is then played back over
+/// and over. Each time the signal goes around the loop it's low-pass filtered and slightly attenuated, so the
+/// high harmonics die away first and the sound decays like a string.
+/// 
+/// [`Waveform::Pluck`]:    super::wave::Waveform::Pluck
+pub struct Pluck {
+    /// How much `[0..1]` of the high harmonics survive each trip around the loop. At 0, the loop filter is the
+    /// classic two-sample average, and at 1 the loop isn't filtered at all.
+    brightness: f64,
+    /// The delay line holding one period of the string's vibration.
+    buffer: Vec<f64>,
+    /// How quickly `[0..1]` the string decays, from a 10 second ring at 0 to a short, muted thump at 1.
+    damping: f64,
+    /// The previous sample read from the delay line, used by the loop filter.
+    last: f64,
+    /// Where `[0..0.5]` along the string it's plucked, from the end to the middle.
+    /// Plucking closer to the middle removes more harmonics.
+    position: f64,
+    /// The index in `self.buffer` that the next sample is written to.
+    write: usize,
+}
+
+impl Pluck {
+    /// Returns a new, silent `Pluck`.
+    pub fn new() -> Pluck {
+        Pluck {
+            brightness: 0.5,
+            buffer: vec![0.0; BUFFER_LENGTH],
+            damping: 0.5,
+            last: 0.0,
+            position: 0.0,
+            write: 0,
+        }
+    }
+
+    /// Plucks the string by adding one period of samples from `exciter` to the delay line, shaped by
+    /// `self.position` with a comb filter. Any DC offset in the excitation is removed, so that colored noise can
+    /// be used without the string drifting away from 0. The burst lands on top of whatever the string is already
+    /// playing, so plucking a ringing string doesn't cut it off.
+    pub fn excite(&mut self, frequency: f64, sample_rate: f64, mut exciter: impl FnMut() -> f64) {
+        let length = self.delay(frequency, sample_rate).round() as usize;
+        let start = self.write + BUFFER_LENGTH - length;
+
+        // plucking at a fraction of the string cancels the harmonics with a node at that point, so each sample of
+        // the burst is also subtracted again `offset` samples later
+        let offset = (self.position * length as f64).round() as usize;
+        let mut sum = 0.0;
+        for i in 0..length {
+            let sample = exciter();
+            sum += sample;
+            self.buffer[(start + i) % BUFFER_LENGTH] += sample;
+            if offset > 0 && i + offset < length {
+                self.buffer[(start + i + offset) % BUFFER_LENGTH] -= sample;
+            }
+        }
+
+        // past `offset`, the comb filter has already cancelled the mean along with the rest of the burst
+        let mean = sum / length as f64;
+        for i in 0..length {
+            let sample = &mut self.buffer[(start + i) % BUFFER_LENGTH];
+            if offset == 0 || i < offset {
+                *sample -= mean;
+            }
+            *sample = sample.clamp(-1.0, 1.0);
+        }
+    }
+
+    /// Returns the next sample of the string vibrating at `frequency`, and feeds it back into the delay line.
+    /// 
+    /// The delay is read with linear interpolation, so the pitch can change smoothly (e.g. with pitch bends or
+    /// FM) while the string rings.
+    pub fn next(&mut self, frequency: f64, sample_rate: f64) -> f64 {
+        let frequency = frequency.abs().max(1.0);
+        let delay = self.delay(frequency, sample_rate);
+
+        let read = (self.write as f64 - delay).rem_euclid(BUFFER_LENGTH as f64);
+        let i = read as usize % BUFFER_LENGTH;
+        let fraction = read.fract();
+        let out = self.buffer[i] * (1.0 - fraction) + self.buffer[(i + 1) % BUFFER_LENGTH] * fraction;
+
+        let blend = 0.5 * (1.0 - self.brightness);
+        let filtered = (1.0 - blend) * out + blend * self.last;
+        self.last = out;
+
+        // attenuated once per period so the decay time doesn't depend on the pitch:
+        let decay = MAX_DECAY * f64::powf(0.005, self.damping);
+        let gain = f64::powf(10.0, -3.0 / (decay * frequency));
+
+        self.buffer[self.write] = filtered * gain;
+        self.write = (self.write + 1) % BUFFER_LENGTH;
+
+        out
+    }
+
+    /// Restricts `brightness` to the range `[0..1]`, then replaces `self.brightness`.
+    pub fn set_brightness(&mut self, brightness: f64) {
+        self.brightness = brightness.clamp(0.0, 1.0);
+    }
+
+    /// Restricts `damping` to the range `[0..1]`, then replaces `self.damping`.
+    pub fn set_damping(&mut self, damping: f64) {
+        self.damping = damping.clamp(0.0, 1.0);
+    }
+
+    /// Restricts `position` to the range `[0..0.5]`, then replaces `self.position`. Positions past the middle of
+    /// the string would sound the same as their mirror image.
+    pub fn set_position(&mut self, position: f64) {
+        self.position = position.clamp(0.0, 0.5);
+    }
+
+    /// Returns the length of the loop (in samples) for `frequency`, accounting for the half-sample delay that the
+    /// loop filter adds at full averaging.
+    fn delay(&self, frequency: f64, sample_rate: f64) -> f64 {
+        let filter_delay = 0.5 * (1.0 - self.brightness);
+        (sample_rate / frequency.abs().max(1.0) - filter_delay).clamp(2.0, (BUFFER_LENGTH - 2) as f64)
+    }
+}
+
+#[cfg(test)]
+mod pluck_tests {
+    use super::*;
+
+    /// Plucks with a simple alternating exciter, then returns the first `length` samples at 480Hz.
+    fn pluck(damping: f64, brightness: f64, length: usize) -> Vec<f64> {
+        let mut pluck = Pluck::new();
+        pluck.set_damping(damping);
+        pluck.set_brightness(brightness);
+
+        let mut sign = 1.0;
+        pluck.excite(480.0, 48000.0, || { sign = -sign; sign });
+        (0..length).map(|_| pluck.next(480.0, 48000.0)).collect()
+    }
+
+    fn energy(samples: &[f64]) -> f64 {
+        samples.iter().map(|s| s * s).sum()
+    }
+
+    #[test]
+    fn silent_until_plucked() {
+        let mut pluck = Pluck::new();
+        assert!((0..1000).all(|_| pluck.next(440.0, 48000.0) == 0.0));
+    }
+
+    #[test]
+    fn repeats_once_per_period() {
+        // with no loop filter, each period is the last one scaled by the loop gain
+        let samples = pluck(0.0, 1.0, 1000);
+        let gain = f64::powf(10.0, -3.0 / (MAX_DECAY * 480.0));
+
+        for n in 0..900 {
+            assert!((samples[n + 100] - samples[n] * gain).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn damping_and_darkness_shorten_the_sound() {
+        let tail = |samples: Vec<f64>| energy(&samples[4800..]);
+
+        assert!(tail(pluck(0.8, 0.5, 9600)) < tail(pluck(0.2, 0.5, 9600)));
+        assert!(tail(pluck(0.5, 0.0, 9600)) < tail(pluck(0.5, 1.0, 9600)));
+    }
+
+    #[test]
+    fn constant_excitation_is_removed() {
+        for position in [0.0, 0.25] {
+            let mut pluck = Pluck::new();
+            pluck.set_position(position);
+            pluck.excite(480.0, 48000.0, || 0.5);
+            assert!((0..1000).all(|_| pluck.next(480.0, 48000.0).abs() < 1e-12));
+        }
+    }
+
+    #[test]
+    fn plucking_adds_to_a_ringing_string() {
+        let ring = || {
+            let mut pluck = Pluck::new();
+            let mut sign = 1.0;
+            pluck.excite(480.0, 48000.0, || { sign = -sign; sign });
+            (0..250).for_each(|_| { pluck.next(480.0, 48000.0); });
+            pluck
+        };
+        let mut ringing = ring();
+        let mut replucked = ring();
+
+        // a silent pluck leaves the string ringing exactly as it was:
+        replucked.excite(480.0, 48000.0, || 0.0);
+        for _ in 0..1000 {
+            assert_eq!(replucked.next(480.0, 48000.0), ringing.next(480.0, 48000.0));
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use std::{error::Error, fmt, path::{Path, PathBuf}, sync::Arc};
+
+use crate::synth::tuning::Tuning;
+
+/// Reads every sample from the WAV file at `path`, scaled to the range `[-1..1]`, along with the file's format.
+/// Multi-channel files are mixed down to mono.
+pub fn read_wav(path: &Path) -> Result<(Vec<f64>, hound::WavSpec), Box<dyn Error>> {
+    let mut reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+
+    let samples: Vec<f64> = match spec.sample_format {
+        hound::SampleFormat::Float => {
+            reader.samples::<f32>().map(|s| s.map(f64::from)).collect::<Result<_, _>>()?
+        }
+        hound::SampleFormat::Int => {
+            let scale = f64::powi(2.0, spec.bits_per_sample as i32 - 1);
+            reader.samples::<i32>().map(|s| s.map(|s| s as f64 / scale)).collect::<Result<_, _>>()?
+        }
+    };
+    let channels = spec.channels.max(1) as usize;
+    let samples = samples.chunks_exact(channels).map(|frame| frame.iter().sum::<f64>() / channels as f64).collect();
+
+    Ok((samples, spec))
+}
+
+/// A recording loaded from a WAV file for playback by a [`Sampler`].
+pub struct Sample {
+    /// The file the `Sample` was loaded from.
+    path: PathBuf,
+    /// The sample rate the `Sample` was recorded at.
+    sample_rate: f64,
+    /// The recording's samples.
+    samples: Vec<f32>,
+}
+
+impl Sample {
+    /// Loads a `Sample` from the WAV file at `path`, mixed down to mono by [`read_wav()`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Sample, Box<dyn Error>> {
+        let (samples, spec) = read_wav(path.as_ref())?;
+        let samples: Vec<f32> = samples.into_iter().map(|s| s as f32).collect();
+        if samples.is_empty() {
+            return Err("sample file has no samples".into());
+        }
+
+        Ok(Sample { path: path.as_ref().to_path_buf(), sample_rate: spec.sample_rate as f64, samples })
+    }
+
+    /// Returns the length of the `Sample`, in samples.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Returns the path of the file the `Sample` was loaded from.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+impl Sample {
+    /// Returns a `Sample` of `samples` recorded at `sample_rate`, without loading a file.
+    pub fn from_samples(samples: Vec<f32>, sample_rate: f64) -> Sample {
+        Sample { path: PathBuf::new(), sample_rate, samples }
+    }
+}
+
+impl fmt::Debug for Sample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sample")
+            .field("length", &self.samples.len())
+            .field("path", &self.path)
+            .field("sample_rate", &self.sample_rate)
+            .finish()
+    }
+}
+
+/// Describes what a [`Sampler`] does when playback reaches the end of its loop.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum LoopMode {
+    /// Playback continues to the end of the [`Sample`], then stops.
+    Off,
+    /// Playback jumps from the end of the loop back to its start.
+    Forward,
+    /// Playback reverses direction at each end of the loop.
+    PingPong,
+}
+
+/// Plays a [`Sample`] for [`Waveform::Sample`], restarting each time a MIDI note starts.
+/// 
+/// The `Sample` is assumed to be recorded at the pitch of `root` in the current [`Tuning`], so it's played back
+/// faster or slower to match the [`Oscillator`]'s frequency, like any other waveform.
+/// 
+/// [`Oscillator`]:         super::oscillator::Oscillator
+/// [`Waveform::Sample`]:   super::wave::Waveform::Sample
+pub struct Sampler {
+    /// Whether playback is moving backwards, in [`LoopMode::PingPong`].
+    backwards: bool,
+    /// The end of the loop, as a fraction `[0..1]` of the `Sample`'s length.
+    loop_end: f64,
+    /// The [`LoopMode`] used once playback reaches `loop_end`.
+    loop_mode: LoopMode,
+    /// The start of the loop, as a fraction `[0..1]` of the `Sample`'s length.
+    loop_start: f64,
+    /// Whether a note is being played.
+    playing: bool,
+    /// The current playback position within the `Sample`, in samples.
+    position: f64,
+    /// The MIDI pitch `[0..127]` the `Sample` was recorded at.
+    root: u8,
+    /// The [`Sample`] played, if one has been loaded.
+    sample: Option<Arc<Sample>>,
+    /// Where playback starts, as a fraction `[0..1]` of the `Sample`'s length.
+    start: f64,
+}
+
+impl Sampler {
+    /// Returns a new `Sampler` with no `Sample`, a root note of middle C, and no loop.
+    pub fn new() -> Sampler {
+        Sampler {
+            backwards: false,
+            loop_end: 1.0,
+            loop_mode: LoopMode::Off,
+            loop_start: 0.0,
+            playing: false,
+            position: 0.0,
+            root: 60,
+            sample: None,
+            start: 0.0,
+        }
+    }
+
+    /// Returns the next sample of playback at `frequency`, then advances the playback position. `tuning` gives the
+    /// frequency of `self.root`, at which the `Sample` plays at its original speed.
+    pub fn next(&mut self, frequency: f64, tuning: &Tuning, sample_rate: f64) -> f64 {
+        let Some(sample) = self.sample.as_ref().filter(|_| self.playing) else {
+            return 0.0;
+        };
+        let length = sample.samples.len();
+
+        let i = self.position as usize;
+        let fraction = self.position.fract();
+        let next = sample.samples.get(i + 1).copied().unwrap_or(0.0) as f64;
+        let out = sample.samples[i.min(length - 1)] as f64 * (1.0 - fraction) + next * fraction;
+
+        let rate = frequency.abs() / tuning.frequency(self.root as i32) * sample.sample_rate / sample_rate;
+        let loop_start = self.loop_start * length as f64;
+        let loop_end = self.loop_end * length as f64;
+        let looping = self.loop_mode != LoopMode::Off && loop_end > loop_start;
+
+        if self.backwards {
+            self.position -= rate;
+            if self.position < loop_start {
+                self.position = (2.0 * loop_start - self.position).min(loop_end);
+                self.backwards = false;
+            }
+        } else {
+            self.position += rate;
+            if looping && self.position >= loop_end {
+                match self.loop_mode {
+                    LoopMode::Forward => {
+                        self.position = loop_start + (self.position - loop_end) % (loop_end - loop_start);
+                    }
+                    _ => {
+                        self.position = (2.0 * loop_end - self.position).max(loop_start);
+                        self.backwards = true;
+                    }
+                }
+            } else if self.position >= length as f64 {
+                self.playing = false;
+            }
+        }
+
+        out
+    }
+
+    /// Restricts `end` to the range `[0..1]`, then replaces `self.loop_end`.
+    pub fn set_loop_end(&mut self, end: f64) {
+        self.loop_end = end.clamp(0.0, 1.0);
+    }
+
+    /// Replaces `self.loop_mode` with `mode`.
+    pub fn set_loop_mode(&mut self, mode: LoopMode) {
+        self.loop_mode = mode;
+        self.backwards = false;
+    }
+
+    /// Restricts `start` to the range `[0..1]`, then replaces `self.loop_start`.
+    pub fn set_loop_start(&mut self, start: f64) {
+        self.loop_start = start.clamp(0.0, 1.0);
+    }
+
+    /// Restricts `root` to the range `[0..127]`, then replaces `self.root`.
+    pub fn set_root(&mut self, root: u8) {
+        self.root = root.min(127);
+    }
+
+    /// Replaces `self.sample` with `sample`, stopping any playback of the previous one. Returns the previous
+    /// `Sample`, so that it can be dropped without holding a lock the audio thread waits on.
+    pub fn set_sample(&mut self, sample: Arc<Sample>) -> Option<Arc<Sample>> {
+        self.playing = false;
+        self.sample.replace(sample)
+    }
+
+    /// Restricts `start` to the range `[0..1]`, then replaces `self.start`.
+    pub fn set_start(&mut self, start: f64) {
+        self.start = start.clamp(0.0, 1.0);
+    }
+
+    /// Starts playback from `self.start`.
+    pub fn trigger(&mut self) {
+        if let Some(sample) = &self.sample {
+            self.position = self.start * (sample.samples.len() - 1) as f64;
+            self.backwards = false;
+            self.playing = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod sample_tests {
+    use super::*;
+    use crate::synth::MIDI_TO_HZ;
+
+    /// A `Sampler` playing a ramp of 10 samples recorded at 48kHz, triggered at its root note.
+    fn ramp_sampler(mode: LoopMode) -> Sampler {
+        let mut sampler = Sampler::new();
+        sampler.set_sample(Arc::new(Sample::from_samples((0..10).map(|i| i as f32).collect(), 48000.0)));
+        sampler.set_loop_mode(mode);
+        sampler.set_loop_start(0.2);
+        sampler.set_loop_end(0.6);
+        sampler.trigger();
+        sampler
+    }
+
+    fn play(sampler: &mut Sampler, frequency: f64, length: usize) -> Vec<f64> {
+        (0..length).map(|_| sampler.next(frequency, &Tuning::default(), 48000.0)).collect()
+    }
+
+    #[test]
+    fn stops_at_the_end_without_a_loop() {
+        let mut sampler = ramp_sampler(LoopMode::Off);
+        let root = MIDI_TO_HZ[60];
+
+        assert_eq!(play(&mut sampler, root, 12), [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn loops_forward_and_ping_pong() {
+        let root = MIDI_TO_HZ[60];
+
+        let mut forward = ramp_sampler(LoopMode::Forward);
+        assert_eq!(play(&mut forward, root, 10), [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 2.0, 3.0, 4.0, 5.0]);
+
+        let mut ping_pong = ramp_sampler(LoopMode::PingPong);
+        assert_eq!(play(&mut ping_pong, root, 12), [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 5.0, 4.0, 3.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn pitch_tracks_frequency() {
+        // an octave above the root plays back twice as fast
+        let mut sampler = ramp_sampler(LoopMode::Off);
+        assert_eq!(play(&mut sampler, 2.0 * MIDI_TO_HZ[60], 4), [0.0, 2.0, 4.0, 6.0]);
+
+        let mut sampler = ramp_sampler(LoopMode::Off);
+        sampler.set_start(0.5);
+        sampler.trigger();
+        assert_eq!(play(&mut sampler, MIDI_TO_HZ[60], 2), [4.5, 5.5]);
+    }
+}
@@ -0,0 +1,176 @@
+use std::f64::consts::E;
+
+/// The maximum number of sub-voices a single [`Oscillator`] can stack in unison.
+/// 
+/// [`Oscillator`]: super::oscillator::Oscillator
+pub const MAX_VOICES: usize = 16;
+
+/// Describes how the detuning of unison voices is distributed between the center pitch and the
+/// outermost voices.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum DetuneCurve {
+    /// Voices are detuned in even steps between the center and the outermost voices.
+    Linear,
+    /// Voices are clustered close to the center pitch, with only the outermost voices reaching the full
+    /// detune amount. This produces a "supersaw"-style sound with a stronger fundamental.
+    Exponential,
+}
+
+impl DetuneCurve {
+    /// Maps a voice's position `[-1.0..1.0]` in the unison stack to its share of the detune amount.
+    fn apply(&self, position: f64) -> f64 {
+        match self {
+            DetuneCurve::Linear => position,
+            DetuneCurve::Exponential => {
+                // normalized so that positions of 0.0 and 1.0 are unchanged:
+                position.signum() * (E.powf(3.0 * position.abs()) - 1.0) / (E.powf(3.0) - 1.0)
+            }
+        }
+    }
+}
+
+/// Manages the parameters for stacking several detuned copies of an [`Oscillator`]'s signal.
+/// 
+/// The frequency ratio and stereo gains of each voice are calculated whenever a parameter changes, rather than
+/// during every call to [`Oscillator::calc()`].
+/// 
+/// [`Oscillator`]:         super::oscillator::Oscillator
+/// [`Oscillator::calc()`]: super::oscillator::Oscillator::calc()
+pub struct Unison {
+    /// The [`DetuneCurve`] used to distribute voices between the center pitch and `detune`.
+    curve: DetuneCurve,
+    /// The amount of cents `[0..100]` by which the outermost voices are detuned up or down.
+    detune: f64,
+    /// The left and right channel gains of each voice.
+    pans: [(f64, f64); MAX_VOICES],
+    /// The frequency multiplier of each voice.
+    ratios: [f64; MAX_VOICES],
+    /// How far `[0..1]` the voices are spread across the stereo field. At 0, every voice is centered.
+    spread: f64,
+    /// The number of active voices `[1..16]`.
+    voices: usize,
+}
+
+impl Unison {
+    /// Returns a new `Unison` with a single, centered voice.
+    pub fn new() -> Unison {
+        let mut unison = Unison {
+            curve: DetuneCurve::Linear,
+            detune: 20.0,
+            pans: [(1.0, 1.0); MAX_VOICES],
+            ratios: [1.0; MAX_VOICES],
+            spread: 0.5,
+            voices: 1,
+        };
+        unison.update();
+        unison
+    }
+
+    /// The amplitude multiplier applied to the sum of all voices, so that adding voices doesn't drastically
+    /// increase the volume of the `Oscillator`.
+    pub fn gain(&self) -> f64 {
+        1.0 / (self.voices as f64).sqrt()
+    }
+
+    /// Returns the left and right channel gains for the voice at `index`.
+    pub fn pan(&self, index: usize) -> (f64, f64) {
+        self.pans[index]
+    }
+
+    /// Returns the frequency multiplier for the voice at `index`.
+    pub fn ratio(&self, index: usize) -> f64 {
+        self.ratios[index]
+    }
+
+    /// Replaces `self.curve` with `curve`.
+    pub fn set_curve(&mut self, curve: DetuneCurve) {
+        self.curve = curve;
+        self.update();
+    }
+
+    /// Restricts `detune` to the range `[0..100]`, then replaces `self.detune`.
+    pub fn set_detune(&mut self, detune: f64) {
+        self.detune = detune.clamp(0.0, 100.0);
+        self.update();
+    }
+
+    /// Restricts `spread` to the range `[0..1]`, then replaces `self.spread`.
+    pub fn set_spread(&mut self, spread: f64) {
+        self.spread = spread.clamp(0.0, 1.0);
+        self.update();
+    }
+
+    /// Restricts `voices` to the range `[1..16]`, then replaces `self.voices`.
+    pub fn set_voices(&mut self, voices: usize) {
+        self.voices = voices.clamp(1, MAX_VOICES);
+        self.update();
+    }
+
+    /// Returns the number of active voices.
+    pub fn voices(&self) -> usize {
+        self.voices
+    }
+
+    /// Recalculates `self.ratios` and `self.pans` from the current parameters.
+    /// 
+    /// Voices are laid out evenly from `-1.0` to `1.0`. That position decides the voice's stereo position
+    /// directly, and its detune after being shaped by `self.curve`. Panning uses a balance law, so a centered
+    /// voice is sent at full volume to both channels and a single voice sounds the same as before unison
+    /// was added.
+    fn update(&mut self) {
+        for i in 0..self.voices {
+            let position = if self.voices > 1 {
+                -1.0 + 2.0 * i as f64 / (self.voices - 1) as f64
+            } else {
+                0.0
+            };
+
+            let cents = self.detune * self.curve.apply(position);
+            self.ratios[i] = f64::powf(2.0, cents / 1200.0);
+
+            let pan = position * self.spread;
+            self.pans[i] = ((1.0 - pan).min(1.0), (1.0 + pan).min(1.0));
+        }
+    }
+}
+
+#[cfg(test)]
+mod unison_tests {
+    use super::*;
+
+    #[test]
+    fn single_voice_is_unchanged() {
+        let mut unison = Unison::new();
+        unison.set_detune(100.0);
+        unison.set_spread(1.0);
+
+        assert_eq!(unison.ratio(0), 1.0);
+        assert_eq!(unison.pan(0), (1.0, 1.0));
+        assert_eq!(unison.gain(), 1.0);
+    }
+
+    #[test]
+    fn outer_voices_reach_full_detune() {
+        let mut unison = Unison::new();
+        unison.set_voices(7);
+        unison.set_detune(100.0);
+
+        for curve in [DetuneCurve::Linear, DetuneCurve::Exponential] {
+            unison.set_curve(curve);
+            assert!((unison.ratio(0) - f64::powf(2.0, -1.0 / 12.0)).abs() < 1e-12);
+            assert!((unison.ratio(6) - f64::powf(2.0, 1.0 / 12.0)).abs() < 1e-12);
+            assert_eq!(unison.ratio(3), 1.0);
+        }
+    }
+
+    #[test]
+    fn voices_are_clamped() {
+        let mut unison = Unison::new();
+
+        unison.set_voices(0);
+        assert_eq!(unison.voices(), 1);
+
+        unison.set_voices(100);
+        assert_eq!(unison.voices(), MAX_VOICES);
+    }
+}
@@ -1,5 +1,4 @@
 use crate::PI;
-use rand::{self, Rng};
 
 /// The amount of sine wave harmonics to calculate for generating saw waves. A higher number will be
 /// more "perfect". In the future, this could potentially be configurable at the user level.
@@ -10,7 +9,24 @@ static SAW_HARMONIC_COUNT: usize = 40;
 /// [`Oscillator`]: super::oscillator::Oscillator
 #[derive(Clone,Debug,PartialEq)]
 pub enum Waveform {
+    /// A [`Wavetable`] built from individually set harmonics.
+    /// 
+    /// [`Wavetable`]:  super::wavetable::Wavetable
+    Additive,
+    /// A user-supplied [`Wavetable`] loaded into the `Oscillator`.
+    /// 
+    /// [`Wavetable`]:  super::wavetable::Wavetable
+    Custom,
     Noise,
+    /// A plucked string, modeled with a [`Pluck`] that's excited by each MIDI note.
+    /// 
+    /// [`Pluck`]:  super::pluck::Pluck
+    Pluck,
+    Pulse,
+    /// A user-supplied recording, played back by a [`Sampler`] that's restarted by each MIDI note.
+    /// 
+    /// [`Sampler`]:    super::sample::Sampler
+    Sample,
     Saw,
     Sine,
     Square,
@@ -26,8 +42,11 @@ impl Waveform {
     /// [`Oscillator`]:         super::oscillator::Oscillator
     pub fn calc(&self, delta: f64, freq: f64) -> f64 {
         match self {
-            // White noise, generated via random amplitudes between -1.0 and 1.0.
-            Waveform::Noise => rand::thread_rng().gen::<f64>() * 2.0 - 1.0,
+            // Additive and custom waveforms come from a Wavetable built by the user rather than a formula, so they
+            // have no static table of their own.
+            Waveform::Additive | Waveform::Custom => 0.0,
+            // Noise, plucked strings, and samples have no table either -- Oscillators generate them in real time.
+            Waveform::Noise | Waveform::Pluck | Waveform::Sample => 0.0,
             // Approximation of a sawtooth wave using the first 40 harmonics of a sine wave:
             //
             // f(n) = 2 * (sin(1 * 2πfx) + sin(2 * 2πfx) + sin(3 * 2πfx) + ... + sin(n * 2πfx)) / π
@@ -47,21 +66,26 @@ impl Waveform {
             Waveform::Sine => (freq * 2.0 * PI * delta).sin(),
             // A square wave is just a sine wave quantized to binary amplitude values of either 1 or -1.
             // Thus, just calculate it as if it were a sine wave, and then turn it into an if-else.
-            Waveform::Square => if Waveform::Sine.calc(delta, freq) > 0.0 { 1.0 } else { -1.0 },
+            //
+            // Pulse waves depend on an Oscillator's pulse width, so they're generated in real time rather than
+            // from a table -- see Oscillator::calc(). Without a width, they're the same as a square wave.
+            Waveform::Pulse | Waveform::Square => if Waveform::Sine.calc(delta, freq) > 0.0 { 1.0 } else { -1.0 },
             Waveform::Triangle => (Waveform::Sine.calc(delta, freq)).asin() * (2.0 / PI),
         }
     }
 
     /// Retrieve the appropriate sample from the lookup table.
     pub fn get_sample(&self, phase: f64) -> f64 {
-        let i = phase as usize;
+        let i = phase as usize % super::TABLE_LENGTH;
 
         match self {
             Waveform::Saw => super::SAW_TABLE[i],
             Waveform::Sine => super::SINE_TABLE[i],
             Waveform::Square => super::SQUARE_TABLE[i],
             Waveform::Triangle => super::TRI_TABLE[i],
-            _ => panic!(), // this function shouldn't get called by noise oscillators -- see Oscillator::calc()
+            // this function shouldn't get called by additive, custom, noise, pluck, pulse, or sample oscillators --
+            // see Oscillator::calc()
+            _ => panic!(),
         }
     }
 }
\ No newline at end of file
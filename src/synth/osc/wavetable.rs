@@ -0,0 +1,257 @@
+use std::{error::Error, f64::consts::FRAC_PI_2, fmt, path::{Path, PathBuf}};
+
+use realfft::{num_complex::Complex, RealFftPlanner};
+
+use crate::PI;
+
+use super::sample;
+
+/// The number of samples in each frame of a [`Wavetable`], matching the frame size used by most wavetable
+/// synthesizers.
+pub const FRAME_LENGTH: usize = 2048;
+
+/// The number of band-limited copies kept for each frame. Each level has half the harmonics of the one before
+/// it, from `FRAME_LENGTH / 2` down to just the fundamental.
+const LEVELS: usize = FRAME_LENGTH.ilog2() as usize;
+
+/// The number of harmonics that can be set individually with [`Wavetable::from_harmonics()`].
+pub const HARMONICS: usize = 64;
+
+/// A user-supplied waveform, either loaded from a WAV file for [`Waveform::Custom`] or built from individual
+/// harmonics for [`Waveform::Additive`].
+/// 
+/// A file is split into one or more single-cycle frames. Files whose length is a multiple of
+/// [`FRAME_LENGTH`] are treated as a series of frames, and any other file is treated as a single cycle of its
+/// whole length. Each frame is resampled to `FRAME_LENGTH` samples and stored as a set of band-limited
+/// "mipmaps", so that higher notes can read from a copy without the harmonics that would alias at their
+/// frequency.
+/// 
+/// A table loaded from a file keeps its path, so it can be referenced (e.g. by a preset) without storing the
+/// samples themselves.
+/// 
+/// [`Waveform::Additive`]: super::wave::Waveform::Additive
+/// [`Waveform::Custom`]:   super::wave::Waveform::Custom
+pub struct Wavetable {
+    /// Each frame's mipmap levels, from the full bandwidth at index 0 to only the fundamental.
+    frames: Vec<Vec<Vec<f32>>>,
+    /// The file the `Wavetable` was loaded from, if it was.
+    path: Option<PathBuf>,
+}
+
+impl Wavetable {
+    /// Loads a `Wavetable` from the WAV file at `path`. Multi-channel files are mixed down to mono.
+    pub fn load(path: impl AsRef<Path>) -> Result<Wavetable, Box<dyn Error>> {
+        let (samples, _) = sample::read_wav(path.as_ref())?;
+
+        Wavetable::from_samples(&samples, path.as_ref())
+    }
+
+    /// Creates a `Wavetable` from raw `samples`, split into frames as described in [`Wavetable`]. The result is
+    /// normalized so that its loudest sample has an amplitude of 1.
+    pub fn from_samples(samples: &[f64], path: &Path) -> Result<Wavetable, Box<dyn Error>> {
+        if samples.len() < 2 {
+            return Err("wavetable file has no samples".into());
+        }
+        let frame_length = if samples.len().is_multiple_of(FRAME_LENGTH) { FRAME_LENGTH } else { samples.len() };
+
+        let forward = RealFftPlanner::<f64>::new().plan_fft_forward(frame_length);
+        let mut spectra = Vec::with_capacity(samples.len() / frame_length);
+        for frame in samples.chunks_exact(frame_length) {
+            let mut input = frame.to_vec();
+            let mut spectrum = forward.make_output_vec();
+            forward.process(&mut input, &mut spectrum)?;
+            // scaled so that the inverse transform at FRAME_LENGTH reproduces the original amplitude:
+            spectrum.iter_mut().for_each(|bin| *bin /= frame_length as f64);
+            spectra.push(spectrum);
+        }
+
+        let wavetable = Wavetable::from_spectra(&spectra, Some(path.to_path_buf()))?;
+        if wavetable.frames.iter().all(|levels| levels[0].iter().all(|&s| s == 0.0)) {
+            return Err("wavetable file is silent".into());
+        }
+        Ok(wavetable)
+    }
+
+    /// Creates a single-frame `Wavetable` by adding together sine waves at the first [`HARMONICS`] multiples of
+    /// the fundamental, each with the given amplitude `[0..1]` and phase `[0..1]` (as a fraction of a cycle). Any
+    /// missing values are treated as 0. The result is normalized in the same way as [`from_samples()`], unless
+    /// every amplitude is 0.
+    /// 
+    /// [`from_samples()`]: Wavetable::from_samples()
+    pub fn from_harmonics(amplitudes: &[f64], phases: &[f64]) -> Wavetable {
+        let mut spectrum = vec![Complex::default(); FRAME_LENGTH / 2 + 1];
+        for (k, &amplitude) in amplitudes.iter().enumerate().take(HARMONICS) {
+            let phase = phases.get(k).copied().unwrap_or(0.0);
+            // a sine starts a quarter-cycle behind the cosine that each bin of the transform describes:
+            spectrum[k + 1] = Complex::from_polar(amplitude.clamp(0.0, 1.0) / 2.0, 2.0 * PI * phase - FRAC_PI_2);
+        }
+
+        Wavetable::from_spectra(&[spectrum], None).expect("FRAME_LENGTH transforms should always succeed")
+    }
+
+    /// Creates a `Wavetable` with a frame for each of `spectra`, which contain the bins `[0..=FRAME_LENGTH / 2]`
+    /// of each frame's transform. Each frame's mipmap levels are generated by zeroing the bins above their
+    /// highest harmonic.
+    fn from_spectra(spectra: &[Vec<Complex<f64>>], path: Option<PathBuf>) -> Result<Wavetable, Box<dyn Error>> {
+        let inverse = RealFftPlanner::<f64>::new().plan_fft_inverse(FRAME_LENGTH);
+
+        let mut frames = Vec::with_capacity(spectra.len());
+        for spectrum in spectra {
+            let mut levels = Vec::with_capacity(LEVELS);
+            for level in 0..LEVELS {
+                // keeping only harmonics [1..=harmonics] both removes any DC offset and band-limits the level:
+                let harmonics = (FRAME_LENGTH / 2) >> level;
+                let mut bins = inverse.make_input_vec();
+                for (k, bin) in bins.iter_mut().enumerate().take(harmonics + 1).skip(1) {
+                    *bin = spectrum.get(k).copied().unwrap_or_default();
+                }
+                // the inverse transform requires the Nyquist bin to be real:
+                bins[FRAME_LENGTH / 2].im = 0.0;
+
+                let mut output = inverse.make_output_vec();
+                inverse.process(&mut bins, &mut output)?;
+                levels.push(output.into_iter().map(|s| s as f32).collect());
+            }
+            frames.push(levels);
+        }
+
+        let peak = frames.iter()
+            .flat_map(|levels: &Vec<Vec<f32>>| levels[0].iter())
+            .fold(0.0f32, |peak, s| peak.max(s.abs()));
+        if peak > 0.0 {
+            frames.iter_mut().flatten().flatten().for_each(|s| *s /= peak);
+        }
+
+        Ok(Wavetable { frames, path })
+    }
+
+    /// Returns the number of frames in the `Wavetable`.
+    pub fn frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns the sample at `position` `[0..1)` of the cycle, as with [`sample()`], at a point `morph` `[0..1]`
+    /// between the first and last frames. Points between two frames interpolate linearly between them, so the
+    /// timbre changes smoothly as `morph` moves.
+    /// 
+    /// [`sample()`]:   Wavetable::sample()
+    pub fn morph(&self, morph: f64, position: f64, dt: f64) -> f64 {
+        let frame = morph.clamp(0.0, 1.0) * (self.frames.len() - 1) as f64;
+        let i = frame as usize;
+        let fraction = frame.fract();
+
+        if fraction == 0.0 {
+            self.sample(i, position, dt)
+        } else {
+            self.sample(i, position, dt) * (1.0 - fraction) + self.sample(i + 1, position, dt) * fraction
+        }
+    }
+
+    /// Returns the path of the file the `Wavetable` was loaded from, if it was.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Returns the sample of `frame` at `position` `[0..1)` of the cycle, interpolating linearly between the
+    /// stored samples.
+    /// 
+    /// `dt` is how far the position moves each sample, which decides the mipmap level read from: the one with
+    /// the most harmonics that all stay below the Nyquist frequency.
+    pub fn sample(&self, frame: usize, position: f64, dt: f64) -> f64 {
+        let levels = &self.frames[frame.min(self.frames.len() - 1)];
+        // level n keeps FRAME_LENGTH / 2^(n + 1) harmonics, which stay below Nyquist while that times dt <= 0.5:
+        let level = (FRAME_LENGTH as f64 * dt.abs()).log2().ceil().clamp(0.0, (LEVELS - 1) as f64) as usize;
+        let table = &levels[level];
+
+        let index = position.rem_euclid(1.0) * FRAME_LENGTH as f64;
+        let i = index as usize % FRAME_LENGTH;
+        let fraction = index.fract();
+        let next = table[(i + 1) % FRAME_LENGTH] as f64;
+
+        table[i] as f64 * (1.0 - fraction) + next * fraction
+    }
+}
+
+impl fmt::Debug for Wavetable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Wavetable")
+            .field("frames", &self.frames.len())
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod wavetable_tests {
+    use super::*;
+
+    /// One cycle of a naive sawtooth, which has harmonics all the way up to Nyquist.
+    fn saw(length: usize) -> Vec<f64> {
+        (0..length).map(|i| 2.0 * i as f64 / length as f64 - 1.0).collect()
+    }
+
+    #[test]
+    fn frames_are_split_by_length() {
+        let path = Path::new("test.wav");
+
+        assert_eq!(Wavetable::from_samples(&saw(FRAME_LENGTH).repeat(4), path).unwrap().frames(), 4);
+        assert_eq!(Wavetable::from_samples(&saw(600), path).unwrap().frames(), 1);
+        assert!(Wavetable::from_samples(&[0.0; FRAME_LENGTH], path).is_err());
+    }
+
+    #[test]
+    fn morph_interpolates_between_frames() {
+        let mut samples = vec![0.5; FRAME_LENGTH];
+        samples.extend(saw(FRAME_LENGTH));
+        let table = Wavetable::from_samples(&samples, Path::new("test.wav")).unwrap();
+
+        // the first frame is only DC, which is removed when it's loaded:
+        for position in [0.1, 0.3, 0.6] {
+            let last = table.sample(1, position, 0.0);
+            assert_eq!(table.morph(0.0, position, 0.0), 0.0);
+            assert!((table.morph(0.5, position, 0.0) - last / 2.0).abs() < 1e-6);
+            assert_eq!(table.morph(1.0, position, 0.0), last);
+        }
+    }
+
+    #[test]
+    fn single_cycles_are_resampled() {
+        let sine: Vec<f64> = (0..600).map(|i| (2.0 * PI * i as f64 / 600.0).sin()).collect();
+        let table = Wavetable::from_samples(&sine, Path::new("test.wav")).unwrap();
+
+        for i in 0..16 {
+            let position = i as f64 / 16.0;
+            assert!((table.sample(0, position, 0.0) - (2.0 * PI * position).sin()).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn harmonics_are_added_in_phase() {
+        // a fundamental and its second harmonic at half amplitude, with the harmonic shifted by a quarter-cycle:
+        let table = Wavetable::from_harmonics(&[1.0, 0.5], &[0.0, 0.25]);
+        let expected = |t: f64| (2.0 * PI * t).sin() + 0.5 * (4.0 * PI * t + PI / 2.0).sin();
+        let peak = (0..FRAME_LENGTH).map(|i| expected(i as f64 / FRAME_LENGTH as f64).abs()).fold(0.0, f64::max);
+
+        for i in 0..16 {
+            let position = i as f64 / 16.0;
+            assert!((table.sample(0, position, 0.0) - expected(position) / peak).abs() < 1e-4);
+        }
+
+        let silent = Wavetable::from_harmonics(&[0.0; HARMONICS], &[]);
+        assert_eq!(silent.sample(0, 0.25, 0.0), 0.0);
+    }
+
+    #[test]
+    fn high_notes_read_band_limited_levels() {
+        let table = Wavetable::from_samples(&saw(FRAME_LENGTH), Path::new("test.wav")).unwrap();
+
+        // above a quarter of the sample rate only the fundamental fits below Nyquist, so the level is a pure sine:
+        let dt = 0.3;
+        for i in 0..16 {
+            let position = i as f64 / 16.0;
+            let fundamental = -(2.0 * PI * position).sin();
+            let peak = table.sample(0, 0.75, dt).abs();
+            assert!((table.sample(0, position, dt) - fundamental * peak).abs() < 1e-3);
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use super::NUM_OSCS;
+
+/// The property of an [`Oscillator`] that another [`Oscillator`]'s signal modulates.
+/// 
+/// [`Oscillator`]: super::osc::oscillator::Oscillator
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum ModKind {
+    /// The source's signal modifies the target's frequency (or phase, depending on its `FmMode`).
+    Fm,
+    /// The source's signal modifies the target's pulse width.
+    Pwm,
+    /// The source resets the target's phase every time it completes a cycle.
+    Sync,
+    /// The source's signal scales the target's amplitude.
+    Am,
+    /// The source's signal is multiplied with the target's signal.
+    Ring,
+}
+
+/// A single connection from one [`Oscillator`] to another in the [`Routing`] matrix.
+/// 
+/// [`Oscillator`]: super::osc::oscillator::Oscillator
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct Route {
+    /// What the source modulates in the target.
+    pub kind: ModKind,
+    /// How strongly `[0..1]` the source modulates the target.
+    pub depth: f64,
+}
+
+impl Route {
+    /// Scales a modulator's `sample` by `self.depth` before it's sent to the target.
+    /// 
+    /// As the depth decreases, the sample moves towards whichever value leaves the target unmodified: 0 for
+    /// FM and PWM, which add to the target's frequency or width, and 1 for AM and ring modulation, which
+    /// multiply its amplitude. Sync ignores the depth.
+    /// 
+    /// For AM and ring modulation, the target's own `am_depth` is applied on top of this, so the two depths
+    /// multiply: a route at 50% into a target at 50% modulates it as deeply as a single depth of 25%. The route's
+    /// depth sets how much of each source is used, and the target's how much modulation it accepts in total.
+    pub fn apply(&self, sample: f64) -> f64 {
+        match self.kind {
+            ModKind::Am | ModKind::Ring => 1.0 - self.depth + self.depth * sample,
+            _ => self.depth * sample,
+        }
+    }
+}
+
+/// A named preset for the whole [`Routing`] matrix, connecting the [`Oscillator`]s as FM operators in one of the
+/// common arrangements used by FM synthesizers.
+/// 
+/// In the descriptions below, `Oscillator`s are numbered from 1 as in the UI, and "carriers" are the ones sent to
+/// the [`Mixer`].
+/// 
+/// [`Mixer`]:      super::mixer::Mixer
+/// [`Oscillator`]: super::osc::oscillator::Oscillator
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Algorithm {
+    /// Every `Oscillator` is a carrier, with no modulation.
+    Parallel,
+    /// A single stack: 4 modulates 3, which modulates 2, which modulates carrier 1.
+    Serial,
+    /// Two independent pairs: 2 modulates carrier 1, and 4 modulates carrier 3.
+    Pairs,
+    /// 2, 3, and 4 all modulate carrier 1.
+    ThreeToOne,
+    /// A three-operator stack (3 modulates 2, which modulates carrier 1), plus 4 as an unmodulated carrier.
+    StackPlusOne,
+    /// 4 modulates each of carriers 1, 2, and 3.
+    OneToThree,
+    /// 4 modulates both 2 and 3, which both modulate carrier 1.
+    Diamond,
+}
+
+impl Algorithm {
+    /// Returns whether each `Oscillator` is a carrier in this `Algorithm`.
+    pub fn master(&self) -> [bool; NUM_OSCS] {
+        match self {
+            Algorithm::Parallel => [true, true, true, true],
+            Algorithm::Pairs => [true, false, true, false],
+            Algorithm::StackPlusOne => [true, false, false, true],
+            Algorithm::OneToThree => [true, true, true, false],
+            _ => [true, false, false, false],
+        }
+    }
+
+    /// Returns every `(source, target)` pair of `Oscillator` indexes connected by FM in this `Algorithm`.
+    pub fn routes(&self) -> &'static [(usize, usize)] {
+        match self {
+            Algorithm::Parallel => &[],
+            Algorithm::Serial => &[(3, 2), (2, 1), (1, 0)],
+            Algorithm::Pairs => &[(1, 0), (3, 2)],
+            Algorithm::ThreeToOne => &[(1, 0), (2, 0), (3, 0)],
+            Algorithm::StackPlusOne => &[(2, 1), (1, 0)],
+            Algorithm::OneToThree => &[(3, 0), (3, 1), (3, 2)],
+            Algorithm::Diamond => &[(3, 1), (3, 2), (1, 0), (2, 0)],
+        }
+    }
+}
+
+/// Describes where each [`Oscillator`]'s signal is sent: to the [`Mixer`], to any number of other
+/// `Oscillator`s as modulation, or both.
+/// 
+/// Every time a route changes, the `Oscillator`s are sorted so that each one is evaluated after everything
+/// that modulates it, letting modulation reach its target within the same sample. Routes that are part of a
+/// true cycle (where the target also modulates the source, directly or through other `Oscillator`s) can't be
+/// satisfied that way, so every route within a cycle is explicitly delayed by one sample: its target reads the
+/// source's output from the previous sample, no matter which of them is evaluated first. This means a patch
+/// sounds the same regardless of which slots its `Oscillator`s occupy.
+/// 
+/// [`Mixer`]:      super::mixer::Mixer
+/// [`Oscillator`]: super::osc::oscillator::Oscillator
+pub struct Routing {
+    /// Whether each route (indexed the same as `self.routes`) is part of a cycle, and so is delayed by a sample.
+    delayed: [[bool; NUM_OSCS]; NUM_OSCS],
+    /// The output sample and [`sync_out()`] of each `Oscillator` from the previous sample, used by delayed routes.
+    /// 
+    /// [`sync_out()`]: super::osc::oscillator::Oscillator::sync_out()
+    history: [(f64, Option<f64>); NUM_OSCS],
+    /// Whether each `Oscillator` is sent to the [`Mixer`].
+    /// 
+    /// [`Mixer`]:  super::mixer::Mixer
+    master: [bool; NUM_OSCS],
+    /// The order in which the `Oscillator`s should be evaluated.
+    order: [usize; NUM_OSCS],
+    /// The [`Route`], if any, from each source `Oscillator` (the first index) to each target.
+    routes: [[Option<Route>; NUM_OSCS]; NUM_OSCS],
+}
+
+impl Routing {
+    /// Returns a new `Routing` where every `Oscillator` is sent only to the [`Mixer`].
+    /// 
+    /// [`Mixer`]:  super::mixer::Mixer
+    pub fn new() -> Routing {
+        Routing {
+            delayed: [[false; NUM_OSCS]; NUM_OSCS],
+            history: [(0.0, None); NUM_OSCS],
+            master: [true; NUM_OSCS],
+            order: std::array::from_fn(|i| i),
+            routes: [[None; NUM_OSCS]; NUM_OSCS],
+        }
+    }
+
+    /// Returns every `(source, Route)` pair connected to `target` that is delayed by one sample.
+    pub fn delayed_into(&self, target: usize) -> impl Iterator<Item = (usize, Route)> + '_ {
+        (0..NUM_OSCS).filter_map(move |source| match self.routes[source][target] {
+            Some(route) if self.delayed[source][target] => Some((source, route)),
+            _ => None,
+        })
+    }
+
+    /// Returns the output sample and [`sync_out()`] of the `Oscillator` at `index` from the previous sample.
+    /// 
+    /// [`sync_out()`]: super::osc::oscillator::Oscillator::sync_out()
+    pub fn history(&self, index: usize) -> (f64, Option<f64>) {
+        self.history[index]
+    }
+
+    /// Returns whether the `Oscillator` at `index` is sent to the [`Mixer`].
+    /// 
+    /// [`Mixer`]:  super::mixer::Mixer
+    pub fn master(&self, index: usize) -> bool {
+        self.master[index]
+    }
+
+    /// Returns the order in which the `Oscillator`s should be evaluated.
+    pub fn order(&self) -> [usize; NUM_OSCS] {
+        self.order
+    }
+
+    /// Returns every `(target, Route)` pair that `source` is connected to without a delay.
+    pub fn routes_from(&self, source: usize) -> impl Iterator<Item = (usize, Route)> + '_ {
+        (0..NUM_OSCS).filter_map(move |target| match self.routes[source][target] {
+            Some(route) if !self.delayed[source][target] => Some((target, route)),
+            _ => None,
+        })
+    }
+
+    /// Replaces the history used by delayed routes with this sample's outputs. See [`history()`].
+    /// 
+    /// [`history()`]:  Routing::history()
+    pub fn set_history(&mut self, history: [(f64, Option<f64>); NUM_OSCS]) {
+        self.history = history;
+    }
+
+    /// Sets whether the `Oscillator` at `index` is sent to the [`Mixer`].
+    /// 
+    /// [`Mixer`]:  super::mixer::Mixer
+    pub fn set_master(&mut self, index: usize, send: bool) {
+        self.master[index] = send;
+    }
+
+    /// Replaces the [`Route`] from `source` to `target`, returning the previous one, and recalculates the
+    /// evaluation order. `Oscillator`s can't be routed to themselves, so if `source` and `target` are the same,
+    /// nothing changes.
+    pub fn set_route(&mut self, source: usize, target: usize, route: Option<Route>) -> Option<Route> {
+        if source == target {
+            return None;
+        }
+        let old = std::mem::replace(&mut self.routes[source][target], route);
+        self.sort();
+        old
+    }
+
+    /// Recalculates `self.delayed` and `self.order`.
+    /// 
+    /// A route is part of a cycle if its target can reach its source again by following other routes. Once
+    /// those routes are set aside, what remains can be sorted topologically with Kahn's algorithm. Ties are
+    /// broken by index, which only decides the order of `Oscillator`s that don't affect each other.
+    fn sort(&mut self) {
+        // reachable[i][j] is true if i modulates j, directly or through other Oscillators:
+        let mut reachable: [[bool; NUM_OSCS]; NUM_OSCS] =
+            std::array::from_fn(|i| std::array::from_fn(|j| self.routes[i][j].is_some()));
+        for k in 0..NUM_OSCS {
+            for i in 0..NUM_OSCS {
+                for j in 0..NUM_OSCS {
+                    reachable[i][j] |= reachable[i][k] && reachable[k][j];
+                }
+            }
+        }
+
+        self.delayed = std::array::from_fn(|i| std::array::from_fn(|j| {
+            self.routes[i][j].is_some() && reachable[j][i]
+        }));
+
+        let mut inputs: [usize; NUM_OSCS] = std::array::from_fn(|j| {
+            (0..NUM_OSCS).filter(|&i| self.routes[i][j].is_some() && !self.delayed[i][j]).count()
+        });
+        let mut sorted = [false; NUM_OSCS];
+
+        for position in 0..NUM_OSCS {
+            // with cyclic routes set aside there's always an Oscillator with no remaining inputs
+            let next = (0..NUM_OSCS)
+                .find(|&i| !sorted[i] && inputs[i] == 0)
+                .expect("routing should be acyclic once delayed routes are removed");
+
+            sorted[next] = true;
+            self.order[position] = next;
+            for (target, _) in self.routes_from(next) {
+                inputs[target] -= 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod routing_tests {
+    use super::*;
+
+    fn fm() -> Option<Route> {
+        Some(Route { kind: ModKind::Fm, depth: 1.0 })
+    }
+
+    #[test]
+    fn modulators_are_evaluated_before_targets() {
+        let mut routing = Routing::new();
+        routing.set_route(3, 2, fm());
+        routing.set_route(2, 1, fm());
+        routing.set_route(1, 0, fm());
+
+        assert_eq!(routing.order(), [3, 2, 1, 0]);
+        assert_eq!(routing.delayed_into(0).count(), 0);
+    }
+
+    #[test]
+    fn only_cycles_are_delayed() {
+        let mut routing = Routing::new();
+        routing.set_route(0, 1, fm());
+        routing.set_route(1, 2, fm());
+        routing.set_route(2, 0, fm());
+        routing.set_route(3, 0, fm());
+
+        for (source, target) in [(0, 1), (1, 2), (2, 0)] {
+            assert!(routing.delayed[source][target]);
+        }
+        assert!(!routing.delayed[3][0]);
+
+        let order = routing.order();
+        let position = |i| order.iter().position(|&j| j == i).unwrap();
+        assert!(position(3) < position(0));
+    }
+
+    #[test]
+    fn algorithms_have_no_delayed_routes() {
+        for algorithm in [
+            Algorithm::Parallel, Algorithm::Serial, Algorithm::Pairs, Algorithm::ThreeToOne,
+            Algorithm::StackPlusOne, Algorithm::OneToThree, Algorithm::Diamond,
+        ] {
+            let mut routing = Routing::new();
+            for &(source, target) in algorithm.routes() {
+                routing.set_route(source, target, fm());
+            }
+
+            for target in 0..NUM_OSCS {
+                assert_eq!(routing.delayed_into(target).count(), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn removing_a_route_breaks_the_cycle() {
+        let mut routing = Routing::new();
+        routing.set_route(0, 1, fm());
+        routing.set_route(1, 0, fm());
+        routing.set_route(1, 0, None);
+
+        assert!(!routing.delayed[0][1]);
+        assert_eq!(routing.order()[0], 0);
+    }
+}
@@ -0,0 +1,341 @@
+use std::{error::Error, fs, path::Path};
+
+use super::MIDI_TO_HZ;
+
+/// The number of MIDI notes `[0..127]` that a [`Tuning`] assigns a frequency to.
+pub const NOTES: usize = 128;
+
+/// A musical scale, as described by a Scala `.scl` file.
+/// 
+/// Each degree is stored in cents above the scale's first note, which isn't stored itself (it's always 0). The
+/// last degree is the scale's period -- usually an octave of 1200 cents -- after which the scale repeats.
+#[derive(Clone,Debug,PartialEq)]
+pub struct Scale {
+    /// The pitch (in cents) of each degree after the first.
+    degrees: Vec<f64>,
+    /// The description of the scale, from the first line of its file.
+    description: String,
+}
+
+impl Scale {
+    /// Returns the standard 12-tone equal tempered scale.
+    pub fn equal_temperament() -> Scale {
+        Scale {
+            degrees: (1..=12).map(|i| i as f64 * 100.0).collect(),
+            description: "12-tone equal temperament".into(),
+        }
+    }
+
+    /// Loads a `Scale` from the Scala `.scl` file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Scale, Box<dyn Error>> {
+        Scale::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses the contents of a Scala `.scl` file.
+    /// 
+    /// Lines starting with `!` are comments. The first other line is the description, the next is the number of
+    /// degrees, and each line after that is a degree: in cents if it contains a period (e.g. `701.955`), or
+    /// otherwise as a ratio (e.g. `3/2`) or a whole number (e.g. `2`, meaning `2/1`). Anything after the first
+    /// word of a line is ignored.
+    pub fn parse(text: &str) -> Result<Scale, Box<dyn Error>> {
+        let mut lines = text.lines().filter(|line| !line.starts_with('!'));
+
+        let description = lines.next().ok_or("scale file is empty")?.trim().to_string();
+        let count: usize = lines.next()
+            .and_then(|line| line.split_whitespace().next())
+            .ok_or("scale file has no note count")?
+            .parse()?;
+        if count == 0 {
+            return Err("scale has no notes".into());
+        }
+
+        let degrees = lines.take(count)
+            .map(|line| parse_degree(line.split_whitespace().next().unwrap_or("")))
+            .collect::<Result<Vec<f64>, _>>()?;
+        if degrees.len() < count {
+            return Err(format!("scale file lists {} of its {count} notes", degrees.len()).into());
+        }
+
+        Ok(Scale { degrees, description })
+    }
+
+    /// Returns the description of the `Scale`.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Returns the number of degrees in the `Scale`, including the period.
+    pub fn len(&self) -> usize {
+        self.degrees.len()
+    }
+
+    /// Returns the pitch (in cents) of `step` degrees above the scale's first note, continuing through as many
+    /// periods as needed in either direction.
+    fn cents(&self, step: i32) -> f64 {
+        let size = self.degrees.len() as i32;
+        let period = self.degrees[self.degrees.len() - 1];
+
+        let degree = step.rem_euclid(size) as usize;
+        let offset = if degree == 0 { 0.0 } else { self.degrees[degree - 1] };
+        step.div_euclid(size) as f64 * period + offset
+    }
+}
+
+/// Parses a single degree of a Scala `.scl` file into cents. See [`Scale::parse()`].
+fn parse_degree(word: &str) -> Result<f64, Box<dyn Error>> {
+    if word.contains('.') {
+        return Ok(word.parse()?);
+    }
+
+    let (numerator, denominator) = word.split_once('/').unwrap_or((word, "1"));
+    let numerator: f64 = numerator.parse::<u64>()? as f64;
+    let denominator: f64 = denominator.parse::<u64>()? as f64;
+    if numerator == 0.0 || denominator == 0.0 {
+        return Err(format!("invalid ratio in scale file: {word}").into());
+    }
+
+    Ok(1200.0 * f64::log2(numerator / denominator))
+}
+
+/// Assigns the degrees of a [`Scale`] to MIDI notes, as described by a Scala `.kbm` file.
+#[derive(Clone,Debug,PartialEq)]
+pub struct KeyboardMapping {
+    /// The lowest MIDI note that's mapped.
+    first_note: u8,
+    /// The highest MIDI note that's mapped.
+    last_note: u8,
+    /// The scale degree (if any) played by each key of one repeat of the mapping, starting from `middle_note`.
+    /// If this is empty, every key plays the next degree of the scale.
+    map: Vec<Option<i32>>,
+    /// The MIDI note that plays the scale's first degree.
+    middle_note: u8,
+    /// The scale degree that the mapping repeats at, usually the scale's period. If this is 0, the scale's period
+    /// is used.
+    octave_degree: i32,
+    /// The frequency (in Hz) of `reference_note`.
+    reference_frequency: f64,
+    /// The MIDI note that's tuned to exactly `reference_frequency`, which the rest of the keyboard is tuned from.
+    reference_note: u8,
+}
+
+impl KeyboardMapping {
+    /// Returns a mapping where every key plays the next degree of the scale, middle C (MIDI note 60) plays its
+    /// first degree, and A4 (MIDI note 69) is tuned to `reference_frequency`.
+    pub fn linear(reference_frequency: f64) -> KeyboardMapping {
+        KeyboardMapping {
+            first_note: 0,
+            last_note: 127,
+            map: vec![],
+            middle_note: 60,
+            octave_degree: 0,
+            reference_frequency,
+            reference_note: 69,
+        }
+    }
+
+    /// Loads a `KeyboardMapping` from the Scala `.kbm` file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<KeyboardMapping, Box<dyn Error>> {
+        KeyboardMapping::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses the contents of a Scala `.kbm` file.
+    /// 
+    /// Lines starting with `!` are comments. The other lines are, in order: the size of the map, the first and
+    /// last MIDI notes to retune, the note that plays the scale's first degree, the reference note, its
+    /// frequency, the scale degree the map repeats at, and then one line per key of the map with the degree it
+    /// plays, or `x` if the key is unmapped. Missing map entries are treated as unmapped.
+    pub fn parse(text: &str) -> Result<KeyboardMapping, Box<dyn Error>> {
+        let mut words = text.lines()
+            .filter(|line| !line.starts_with('!') && !line.trim().is_empty())
+            .map(|line| line.split_whitespace().next().unwrap_or(""));
+        let mut next = |name: &str| words.next().ok_or(format!("keyboard mapping file has no {name}"));
+
+        let size: usize = next("map size")?.parse()?;
+        let first_note: u8 = next("first note")?.parse()?;
+        let last_note: u8 = next("last note")?.parse()?;
+        let middle_note: u8 = next("middle note")?.parse()?;
+        let reference_note: u8 = next("reference note")?.parse()?;
+        let reference_frequency: f64 = next("reference frequency")?.parse()?;
+        let octave_degree: i32 = next("octave degree")?.parse()?;
+
+        let mut map = Vec::with_capacity(size);
+        for _ in 0..size {
+            map.push(match next("map entry") {
+                Ok("x") | Err(_) => None,
+                Ok(degree) => Some(degree.parse()?),
+            });
+        }
+
+        if [first_note, last_note, middle_note, reference_note].iter().any(|&note| note as usize >= NOTES) {
+            return Err("keyboard mapping notes must be in the range 0-127".into());
+        }
+        if reference_frequency <= 0.0 {
+            return Err("keyboard mapping reference frequency must be positive".into());
+        }
+
+        Ok(KeyboardMapping {
+            first_note,
+            last_note,
+            map,
+            middle_note,
+            octave_degree,
+            reference_frequency,
+            reference_note,
+        })
+    }
+
+    /// Returns the frequency (in Hz) of the mapping's reference note.
+    pub fn reference_frequency(&self) -> f64 {
+        self.reference_frequency
+    }
+
+    /// Returns the MIDI note the mapping tunes to its reference frequency.
+    pub fn reference_note(&self) -> u8 {
+        self.reference_note
+    }
+
+    /// Restricts `frequency` to the range `[1..20000]`, then replaces `self.reference_frequency`.
+    pub fn set_reference_frequency(&mut self, frequency: f64) {
+        self.reference_frequency = frequency.clamp(1.0, 20000.0);
+    }
+
+    /// Returns the pitch (in cents, relative to the scale's first degree) that `note` plays in `scale`, or `None`
+    /// if the key is unmapped.
+    fn cents(&self, scale: &Scale, note: u8) -> Option<f64> {
+        if note < self.first_note || note > self.last_note {
+            return None;
+        }
+        let offset = note as i32 - self.middle_note as i32;
+
+        if self.map.is_empty() {
+            return Some(scale.cents(offset));
+        }
+
+        let size = self.map.len() as i32;
+        let degree = self.map[offset.rem_euclid(size) as usize]?;
+        let octave = if self.octave_degree == 0 { scale.len() as i32 } else { self.octave_degree };
+        Some(offset.div_euclid(size) as f64 * scale.cents(octave) + scale.cents(degree))
+    }
+}
+
+/// The frequency of every MIDI note, used by each [`Oscillator`]'s pitch calculations. The default `Tuning` is
+/// 12-tone equal temperament with A4 at 440Hz.
+/// 
+/// [`Oscillator`]: super::osc::oscillator::Oscillator
+#[derive(Clone,Debug,PartialEq)]
+pub struct Tuning {
+    /// The frequency (in Hz) of each MIDI note.
+    frequencies: [f64; NOTES],
+}
+
+impl Tuning {
+    /// Returns a `Tuning` that plays `scale` on the keyboard as described by `mapping`.
+    /// 
+    /// Keys that `mapping` leaves unmapped still need a frequency, so they repeat the nearest mapped key below
+    /// them (or above them, at the bottom of the keyboard). Like [`set_frequency()`], every frequency is restricted
+    /// to the range `[1..20000]`, so extreme scales can't produce frequencies the [`Oscillator`]s can't play.
+    /// 
+    /// [`Oscillator`]:         super::osc::oscillator::Oscillator
+    /// [`set_frequency()`]:    Tuning::set_frequency()
+    pub fn new(scale: &Scale, mapping: &KeyboardMapping) -> Result<Tuning, Box<dyn Error>> {
+        let reference = mapping.cents(scale, mapping.reference_note).ok_or("the reference note isn't mapped")?;
+
+        let cents: Vec<Option<f64>> = (0..NOTES as u8).map(|note| mapping.cents(scale, note)).collect();
+        let lowest = cents.iter().flatten().next().copied().ok_or("no notes are mapped")?;
+
+        let mut frequencies = [0.0; NOTES];
+        let mut last = lowest;
+        for (frequency, cents) in frequencies.iter_mut().zip(cents) {
+            last = cents.unwrap_or(last);
+            let unclamped = mapping.reference_frequency * f64::powf(2.0, (last - reference) / 1200.0);
+            *frequency = unclamped.clamp(1.0, 20000.0);
+        }
+
+        Ok(Tuning { frequencies })
+    }
+
+    /// Replaces the frequency of `note` with `frequency`, which is restricted to the range `[1..20000]`. Notes
+    /// outside the range `[0..127]` are ignored.
+    pub fn set_frequency(&mut self, note: u8, frequency: f64) {
+        if let Some(f) = self.frequencies.get_mut(note as usize) {
+            *f = frequency.clamp(1.0, 20000.0);
+        }
+    }
+
+    /// Returns the frequency (in Hz) of `note`, restricted to the range `[0..127]`.
+    pub fn frequency(&self, note: i32) -> f64 {
+        self.frequencies[note.clamp(0, NOTES as i32 - 1) as usize]
+    }
+}
+
+impl Default for Tuning {
+    fn default() -> Tuning {
+        Tuning { frequencies: *MIDI_TO_HZ }
+    }
+}
+
+#[cfg(test)]
+mod tuning_tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-6, "{a} != {b}");
+    }
+
+    #[test]
+    fn equal_temperament_matches_default() {
+        let tuning = Tuning::new(&Scale::equal_temperament(), &KeyboardMapping::linear(440.0)).unwrap();
+
+        for note in 0..NOTES as i32 {
+            assert_close(tuning.frequency(note), Tuning::default().frequency(note));
+        }
+    }
+
+    #[test]
+    fn extreme_scales_are_clamped() {
+        // a period of over 80 octaves overflows within a few keys of the reference:
+        let scale = Scale::parse("huge\n 1\n 100000.0\n").unwrap();
+        let tuning = Tuning::new(&scale, &KeyboardMapping::linear(440.0)).unwrap();
+
+        assert!(tuning.frequencies.iter().all(|&frequency| (1.0..=20000.0).contains(&frequency)));
+        assert_eq!(tuning.frequency(0), 1.0);
+        assert_eq!(tuning.frequency(127), 20000.0);
+    }
+
+    #[test]
+    fn scale_degrees_are_parsed_as_cents_or_ratios() {
+        let scale = Scale::parse("! comment\nPythagorean fragment\n 3\n! another comment\n701.955 fifth\n5/4\n2\n")
+            .unwrap();
+
+        assert_eq!(scale.description(), "Pythagorean fragment");
+        assert_eq!(scale.len(), 3);
+        assert_close(scale.cents(1), 701.955);
+        assert_close(scale.cents(2), 1200.0 * f64::log2(1.25));
+        assert_close(scale.cents(3), 1200.0);
+        assert_close(scale.cents(-2), 701.955 - 1200.0);
+
+        assert!(Scale::parse("Broken\n2\n3/0\n2/1\n").is_err());
+        assert!(Scale::parse("Short\n3\n3/2\n2/1\n").is_err());
+    }
+
+    #[test]
+    fn mapping_skips_unmapped_keys_and_sets_the_reference() {
+        // a pentatonic scale on the white keys, with D4 (62) tuned to 300Hz and black keys unmapped
+        let scale = Scale::parse("Pentatonic\n5\n200.0\n400.0\n700.0\n900.0\n1200.0\n").unwrap();
+        let mapping = KeyboardMapping::parse(
+            "! white keys\n12\n0\n127\n60\n62\n300.0\n5\n0\nx\n1\nx\n2\nx\nx\n3\nx\n4\nx\nx\n"
+        ).unwrap();
+        let tuning = Tuning::new(&scale, &mapping).unwrap();
+
+        assert_close(tuning.frequency(62), 300.0);
+        assert_close(tuning.frequency(60), 300.0 * f64::powf(2.0, -200.0 / 1200.0));
+        assert_close(tuning.frequency(72), 2.0 * tuning.frequency(60));
+        // the unmapped C#4 repeats C4:
+        assert_eq!(tuning.frequency(61), tuning.frequency(60));
+
+        let mut mapping = KeyboardMapping::linear(440.0);
+        mapping.set_reference_frequency(432.0);
+        let tuning = Tuning::new(&Scale::equal_temperament(), &mapping).unwrap();
+        assert_close(tuning.frequency(69), 432.0);
+    }
+}